@@ -1,17 +1,59 @@
-use argon2::{Argon2, Version, Algorithm, Params};
-use tokio::sync::OnceCell;
+use argon2::{Argon2, Version, Algorithm, Params, ParamsBuilder, KeyId};
+use argon2::password_hash::PasswordHash;
+use super::pepper::Peppers;
 use std::env::var;
-use argon2::Error;
 
-static PEPPER: OnceCell<String> = OnceCell::const_new();
 
+/// An `Argon2` that hashes with the current pepper of `peppers` and records its ID in the hashes.
+/// Fails when the configured params are invalid.
+pub fn new_argon2(peppers: &Peppers) -> crate::Result<Argon2<'_>> {
+    let params = params()?;
+    let algorithm = algorithm();
+    let version = version();
+    let argon2 = match peppers.current() {
+        Some((id, pepper)) => {
+            let params = match id {
+                Some(id) => with_keyid(params, id),
+                None => params
+            };
+            match Argon2::new_with_secret(pepper.as_bytes(), algorithm, version, params.clone()) {
+                Ok(argon) => argon,
+                _ => Argon2::new(algorithm, version, params)
+            }
+        },
+        None => Argon2::new(algorithm, version, params)
+    };
+    Ok(argon2)
+}
+
+/// Reads `TIME_COST`, `MEMORY_COST`, `PARALLELISM` and `OUTPUT_LENGTH`, each falling back to its default when it is not set.
+fn params() -> crate::Result<Params> {
+    let t_cost = cost("TIME_COST")?.unwrap_or(Params::DEFAULT_T_COST);
+    let m_cost = cost("MEMORY_COST")?.unwrap_or(Params::DEFAULT_M_COST);
+    let p_cost = cost("PARALLELISM")?.unwrap_or(Params::DEFAULT_P_COST);
+    let output_len = cost("OUTPUT_LENGTH")?.map(|output_len|output_len as usize);
+    Params::new(m_cost, t_cost, p_cost, output_len).map_err(|err|format!("the argon2 params are invalid: {err}").into())
+}
+
+fn cost(name: &str) -> crate::Result<Option<u32>> {
+    match var(name) {
+        Ok(value) => Ok(Some(value.trim().parse().map_err(|_|format!("{name} has to be a number"))?)),
+        _ => Ok(None)
+    }
+}
 
-pub async fn new_argon2() -> Argon2<'static> {
-    let mut t_cost = var("TIME_COST").unwrap_or(Params::DEFAULT_T_COST.to_string()).parse().unwrap_or(Params::DEFAULT_T_COST);
-    let mut m_cost = var("MEMORY_COST").unwrap_or(Params::DEFAULT_M_COST.to_string()).parse().unwrap_or(Params::DEFAULT_M_COST);
-    let mut p_cost = var("PARALLELISM").unwrap_or(Params::DEFAULT_P_COST.to_string()).parse().unwrap_or(Params::DEFAULT_P_COST);
-    let mut output_len = match var("OUTPUT_LENGTH"){Ok(value) => match value.parse(){Ok(value) => Some(value), _ => None}, _ => None};
-    let algorithm = match var("ALGORITHM") {
+fn with_keyid(params: Params, id: KeyId) -> Params {
+    let mut builder = ParamsBuilder::new();
+    builder.m_cost(params.m_cost()).t_cost(params.t_cost()).p_cost(params.p_cost()).keyid(id);
+    if let Some(output_len) = params.output_len() {
+        builder.output_len(output_len);
+    }
+    builder.build().unwrap_or(params)
+}
+
+/// Reads `ALGORITHM`, `argon2d`, `argon2i` or `argon2id`.
+pub(super) fn algorithm() -> Algorithm {
+    match var("ALGORITHM") {
         Ok(value) => {
             match value.to_lowercase().replace(" ", "").as_str() {
                 "argon2d" => Algorithm::Argon2d,
@@ -21,8 +63,12 @@ pub async fn new_argon2() -> Argon2<'static> {
             }
         },
         _ => Algorithm::default()
-    };
-    let version = match var("") {
+    }
+}
+
+/// Reads `VERSION`, `0x10` or `0x13`.
+pub(super) fn version() -> Version {
+    match var("VERSION") {
         Ok(value) => {
             let cleaned_value = value.to_lowercase().replace("version", "").replace("v", "").replace(" ", "");
             match cleaned_value.as_str() {
@@ -32,36 +78,43 @@ pub async fn new_argon2() -> Argon2<'static> {
             }
         },
         _ => Default::default()
-    };
-    let params = (0..10).find_map(|_|{
-        match Params::new(m_cost, t_cost, p_cost, output_len) {
-            Ok(params) => Some(params),
-            Err(err) => {
-                use Error::*;
-                match err {
-                    MemoryTooLittle | MemoryTooMuch => {m_cost = Params::DEFAULT_M_COST; None},
-                    OutputTooShort | OutputTooLong => {output_len = None; None},
-                    ThreadsTooFew | ThreadsTooMany => {p_cost = Params::DEFAULT_P_COST; None},
-                    TimeTooSmall => {t_cost = Params::DEFAULT_T_COST; None},
-                    _ => None
-                }
-            }
-        }
-    }).unwrap_or_default();
-    match PEPPER.get_or_try_init(init).await {
-        Ok(pepper) => {
-            // let secret: &'static [u8] = value.as_bytes();
-            match Argon2::new_with_secret(pepper.as_bytes(), algorithm, version, params.clone()) {
-                Ok(argon) => argon,
-                _ => Argon2::new(algorithm, version, params)
-            }
-        },
-        _ => {
-            Argon2::new(algorithm, version, params)
-        }
     }
 }
 
-async fn init() -> crate::Result<String> {
-    Ok(var("PEPPER")?)
-}
\ No newline at end of file
+/// Whether `hash` was made with a different algorithm, version, params or pepper than `argon2` uses.
+pub fn needs_rehash(hash: &PasswordHash, argon2: &Argon2) -> bool {
+    let params = match Params::try_from(hash) {
+        Ok(params) => params,
+        Err(_) => return true
+    };
+    let current = argon2.params();
+    let output_len = |params: &Params|params.output_len().unwrap_or(Params::DEFAULT_OUTPUT_LEN);
+    Algorithm::try_from(hash.algorithm).ok() != Some(algorithm())
+        || hash.version != Some(version().into())
+        || params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+        || params.keyid() != current.keyid()
+        || hash.hash.map(|output|output.len()).unwrap_or(output_len(&params)) != output_len(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::PasswordHasher;
+    use argon2::password_hash::SaltString;
+
+    #[test]
+    fn test_needs_rehash() {
+        let salt = SaltString::from_b64("c2FsdHNhbHRzYWx0c2FsdA").unwrap();
+        let argon2 = Argon2::new(algorithm(), version(), Params::new(Params::MIN_M_COST * 2, 1, 1, None).unwrap());
+        let hash = argon2.hash_password(b"password", &salt).unwrap();
+        assert!(!needs_rehash(&hash, &argon2));
+        let stronger = Argon2::new(algorithm(), version(), Params::new(Params::MIN_M_COST * 2, 2, 1, None).unwrap());
+        assert!(needs_rehash(&hash, &stronger));
+        let peppered = Argon2::new(algorithm(), version(), with_keyid(argon2.params().clone(), KeyId::new(b"2025").unwrap()));
+        assert!(needs_rehash(&hash, &peppered));
+        let older = Argon2::new(algorithm(), Version::V0x10, Params::new(Params::MIN_M_COST * 2, 1, 1, None).unwrap());
+        assert!(needs_rehash(&older.hash_password(b"password", &salt).unwrap(), &argon2));
+    }
+}
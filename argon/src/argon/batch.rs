@@ -0,0 +1,42 @@
+use shared::{Reply, HashError, Result as Response};
+use std::thread::{available_parallelism, scope};
+use std::panic::resume_unwind;
+
+
+/// Runs `f` on every item, spread over `workers` threads, and returns the results in the order of the items.
+/// The workers are capped by the parallelism of the host. Each worker hashes with its own memory, so a batch takes up to `workers` times `MEMORY_COST`.
+pub fn batch<T: Sync>(items: &[T], workers: u32, f: impl Fn(&T) -> Result<Reply, HashError> + Sync) -> Vec<Response<Reply, HashError>> {
+    let host = available_parallelism().map(|host|host.get()).unwrap_or(1);
+    let workers = (workers as usize).clamp(1, host);
+    let chunk_size = items.len().div_ceil(workers).max(1);
+    scope(|scope| {
+        let workers: Vec<_> = items.chunks(chunk_size).map(|chunk|{
+            scope.spawn(|| chunk.iter().map(|item|f(item).into()).collect::<Vec<_>>())
+        }).collect();
+        workers.into_iter().flat_map(|worker|worker.join().unwrap_or_else(|panic|resume_unwind(panic))).collect()
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_keeps_the_order() {
+        let items: Vec<u32> = (0..10).collect();
+        let results = batch(&items, 3, |item| match item % 2 {
+            0 => Ok(Reply::Hash(item.to_string())),
+            _ => Err(HashError::WrongPassword)
+        });
+        assert_eq!(results.len(), 10);
+        for (item, result) in items.iter().zip(results) {
+            match result {
+                Response::Ok(Reply::Hash(hash)) => assert_eq!(hash, item.to_string()),
+                Response::Err(HashError::WrongPassword) => assert_eq!(item % 2, 1),
+                result => panic!("unexpected {result:?}")
+            }
+        }
+        assert!(batch(&Vec::<u32>::new(), 3, |_|Err(HashError::Unsupported)).is_empty());
+    }
+}
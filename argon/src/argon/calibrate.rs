@@ -0,0 +1,81 @@
+use shared::{Calibration, Calibrated, Benchmark, HashError};
+use super::argon::{algorithm, version};
+use argon2::{Argon2, Params};
+use std::thread::available_parallelism;
+use std::env::var;
+use std::time::Instant;
+
+
+/// Passes are never raised past this, more memory is the better use of the time budget.
+const MAX_T_COST: u32 = 16;
+
+
+/// Benchmarks parameter sets with the configured algorithm and version, and picks the strongest that fits `calibration`.
+/// Memory is halved from the budget until a single pass fits the target latency, then passes are added while they still fit.
+/// The budget is capped at the memory of the function, so a request can't make it run out of memory.
+pub fn calibrate(calibration: &Calibration) -> Result<Calibrated, HashError> {
+    let p_cost = match calibration.parallelism {
+        Some(p_cost) => p_cost,
+        None => available_parallelism().map(|p_cost|p_cost.get() as u32).unwrap_or(Params::DEFAULT_P_COST)
+    };
+    if p_cost > Params::MAX_P_COST {
+        return Err(HashError::InvalidRequest(format!("there can be at most {} lanes", Params::MAX_P_COST)));
+    }
+    let min_m_cost = 8u32.checked_mul(p_cost)
+        .map(|m_cost|m_cost.max(Params::MIN_M_COST))
+        .ok_or_else(||HashError::InvalidRequest(format!("{p_cost} lanes need more memory than can be used")))?;
+    let memory_kib = match function_memory_kib() {
+        Some(function_memory_kib) => calibration.memory_kib.min(function_memory_kib),
+        None => calibration.memory_kib
+    };
+    if memory_kib < min_m_cost {
+        return Err(HashError::InvalidRequest(format!("the memory budget has to be at least {min_m_cost} KiB for {p_cost} lanes")));
+    }
+    let mut benchmarks = Vec::new();
+    let mut best = None;
+    let mut m_cost = memory_kib;
+    while best.is_none() && m_cost >= min_m_cost {
+        for t_cost in 1..=MAX_T_COST {
+            let benchmark = benchmark(m_cost, t_cost, p_cost)?;
+            benchmarks.push(benchmark);
+            if benchmark.elapsed_ms > calibration.target_ms {
+                break;
+            }
+            best = Some(benchmark);
+        }
+        m_cost /= 2;
+    }
+    Ok(Calibrated{best, benchmarks})
+}
+
+/// The memory of the Lambda function in KiB, from `AWS_LAMBDA_FUNCTION_MEMORY_SIZE` which is in MB.
+fn function_memory_kib() -> Option<u32> {
+    var("AWS_LAMBDA_FUNCTION_MEMORY_SIZE").ok()?.parse::<u32>().ok()?.checked_mul(1024)
+}
+
+fn benchmark(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Benchmark, HashError> {
+    let params = Params::new(m_cost, t_cost, p_cost, None).map_err(|err|HashError::InvalidRequest(err.to_string()))?;
+    let argon2 = Argon2::new(algorithm(), version(), params);
+    let mut output = [0u8; Params::DEFAULT_OUTPUT_LEN];
+    let start = Instant::now();
+    argon2.hash_password_into(b"calibration", b"calibration salt", &mut output).map_err(|err|HashError::Internal(err.to_string()))?;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    Ok(Benchmark{m_cost, t_cost, p_cost, elapsed_ms})
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibrate_within_budget() {
+        let calibration = Calibration{target_ms: 1000, memory_kib: 256, parallelism: Some(1)};
+        let calibrated = calibrate(&calibration).unwrap();
+        let best = calibrated.best.unwrap();
+        assert!(best.m_cost <= 256 && best.elapsed_ms <= 1000);
+        assert!(calibrated.benchmarks.contains(&best));
+        assert!(matches!(calibrate(&Calibration{target_ms: 1000, memory_kib: 4, parallelism: Some(1)}), Err(HashError::InvalidRequest(_))));
+        assert!(matches!(calibrate(&Calibration{target_ms: 1000, memory_kib: u32::MAX, parallelism: Some(u32::MAX)}), Err(HashError::InvalidRequest(_))));
+    }
+}
@@ -0,0 +1,50 @@
+use argon2::password_hash::{PasswordHash, PasswordVerifier};
+use super::verify_error;
+use shared::HashError;
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+
+const BCRYPT_PREFIXES: [&str; 4] = ["$2a$", "$2b$", "$2x$", "$2y$"];
+
+
+/// Verifies the hashes of users imported from systems that predate argon: bcrypt, and scrypt and PBKDF2-SHA256 PHC strings.
+/// Returns `None` for any other hash, argon2 hashes included. These hashes are never made, a verified one is replaced by an argon2id hash.
+pub fn verify(password: &str, hash: &str) -> Option<Result<(), HashError>> {
+    if BCRYPT_PREFIXES.iter().any(|prefix|hash.starts_with(prefix)) {
+        return Some(match bcrypt::verify(password, hash) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(HashError::WrongPassword),
+            Err(err) => Err(HashError::InvalidHash(err.to_string()))
+        });
+    }
+    let hash = PasswordHash::new(hash).ok()?;
+    let result = match hash.algorithm.as_str() {
+        "scrypt" => Scrypt.verify_password(password.as_bytes(), &hash),
+        "pbkdf2-sha256" => Pbkdf2.verify_password(password.as_bytes(), &hash),
+        _ => return None
+    };
+    Some(result.map_err(verify_error))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+
+    #[test]
+    fn test_verify_legacy_hashes() {
+        let salt = SaltString::from_b64("c2FsdHNhbHRzYWx0c2FsdA").unwrap();
+        let scrypt = Scrypt.hash_password_customized(b"password", None, None, scrypt::Params::new(4, 1, 1, 32).unwrap(), &salt).unwrap().to_string();
+        let pbkdf2 = Pbkdf2.hash_password_customized(b"password", None, None, pbkdf2::Params{rounds: 1000, output_length: 32}, &salt).unwrap().to_string();
+        let bcrypt = bcrypt::hash("password", 4).unwrap();
+        for hash in [scrypt, pbkdf2, bcrypt] {
+            assert_eq!(verify("password", &hash), Some(Ok(())));
+            assert_eq!(verify("wrong", &hash), Some(Err(HashError::WrongPassword)));
+        }
+        let argon2 = argon2::Argon2::default().hash_password(b"password", &salt).unwrap().to_string();
+        assert_eq!(verify("password", &argon2), None);
+        assert!(matches!(verify("password", "$2b$04$corrupt"), Some(Err(HashError::InvalidHash(_)))));
+    }
+}
@@ -1,31 +1,80 @@
-use argon2::{Argon2, PasswordHasher, PasswordVerifier, password_hash::{SaltString, PasswordHash}};
-use shared::{Request, Result as Response};
+use argon2::{Argon2, PasswordHasher, PasswordVerifier, password_hash::{self, SaltString, PasswordHash}};
+use shared::{Request, AnyRequest, AnyResponse, Reply, Verified, HashError, MAX_BATCH};
 use lambda_runtime::{LambdaEvent, Error};
-pub use argon::new_argon2;
+pub use argon::{new_argon2, needs_rehash};
+pub use pepper::Peppers;
+pub use calibrate::calibrate;
+use batch::batch;
 use rand_core::OsRng;
 
 
 mod argon;
+mod pepper;
+mod legacy;
+mod calibrate;
+mod batch;
 
 
-pub async fn handler(event: LambdaEvent<Request>, argon2: &Argon2<'static>) -> Result<Response<Option<String>, String>, Error> {
-    match event.payload {
-        Request::Hash(password) => {
-            let salt = SaltString::generate(OsRng);
-            match argon2.hash_password(password.as_bytes(), &salt) {
-                Ok(value) => Ok(Response::Ok(Some(value.to_string()))),
-                Err(err) => Ok(Response::Err(err.to_string()))
-            }
+pub async fn handler(event: LambdaEvent<AnyRequest>, argon2: &Argon2<'_>, peppers: &Peppers) -> Result<AnyResponse, Error> {
+    Ok(answer(&event.payload, argon2, peppers))
+}
+
+/// Answers `request` in its own protocol version, requests this build does not know with `HashError::Unsupported`.
+pub fn answer(request: &AnyRequest, argon2: &Argon2<'_>, peppers: &Peppers) -> AnyResponse {
+    let result = match request.request() {
+        Some(known) => respond(known, argon2, peppers),
+        None => Err(HashError::Unsupported)
+    };
+    request.respond(result)
+}
+
+fn respond(request: &Request, argon2: &Argon2<'_>, peppers: &Peppers) -> Result<Reply, HashError> {
+    match request {
+        Request::Hash(password) => hash(password, argon2),
+        Request::Verify(password, hash) => verify(password, hash, argon2, peppers),
+        Request::Calibrate(calibration) => calibrate(calibration).map(Reply::Calibrated),
+        Request::HashBatch(passwords) => {
+            check_batch(passwords.len())?;
+            Ok(Reply::Batch(batch(passwords, argon2.params().p_cost(), |password|hash(password, argon2))))
         },
-        Request::Verify(password, hash) => {
-            let hash = match PasswordHash::new(&hash) {
-                Ok(value) => value,
-                Err(err) => return Ok(Response::Err(err.to_string()))
-            };
-            match argon2.verify_password(password.as_bytes(), &hash) {
-                Ok(_) => Ok(Response::Ok(None)),
-                Err(err) => Ok(Response::Err(err.to_string()))
-            }
+        Request::VerifyBatch(items) => {
+            check_batch(items.len())?;
+            Ok(Reply::Batch(batch(items, argon2.params().p_cost(), |(password, hash)|verify(password, hash, argon2, peppers))))
         }
     }
+}
+
+fn check_batch(len: usize) -> Result<(), HashError> {
+    match len > MAX_BATCH {
+        true => Err(HashError::InvalidRequest(format!("a batch can hold at most {MAX_BATCH} items, not {len}"))),
+        false => Ok(())
+    }
+}
+
+fn hash(password: &str, argon2: &Argon2<'_>) -> Result<Reply, HashError> {
+    let salt = SaltString::generate(OsRng);
+    match argon2.hash_password(password.as_bytes(), &salt) {
+        Ok(value) => Ok(Reply::Hash(value.to_string())),
+        Err(err) => Err(HashError::Internal(err.to_string()))
+    }
+}
+
+fn verify(password: &str, hash: &str, argon2: &Argon2<'_>, peppers: &Peppers) -> Result<Reply, HashError> {
+    if let Some(result) = legacy::verify(password, hash) {
+        return result.map(|_|Reply::Verified(Verified{rehash: true}));
+    }
+    let hash = PasswordHash::new(hash).map_err(|err|HashError::InvalidHash(err.to_string()))?;
+    let verifier = peppers.verifier(&hash)?;
+    match verifier.verify_password(password.as_bytes(), &hash) {
+        Ok(_) => Ok(Reply::Verified(Verified{rehash: needs_rehash(&hash, argon2)})),
+        Err(err) => Err(verify_error(err))
+    }
+}
+
+/// A wrong password is the only failure of a verification that is not the stored hash's fault.
+fn verify_error(err: password_hash::Error) -> HashError {
+    match err {
+        password_hash::Error::Password => HashError::WrongPassword,
+        err => HashError::InvalidHash(err.to_string())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use argon2::{Argon2, KeyId, Params, Algorithm, Version};
+use argon2::password_hash::PasswordHash;
+use shared::HashError;
+use std::env::var;
+
+
+/// The secrets mixed into the hashes.
+/// Each pepper has an ID that argon2 records in the hashes as their `keyid`, so a pepper can be replaced without breaking older hashes.
+#[derive(Default)]
+pub struct Peppers {
+    /// The pepper of hashes without a `keyid`, which predate pepper IDs.
+    legacy: Option<String>,
+    /// Oldest first, the last one peppers new hashes.
+    peppers: Vec<(KeyId, String)>
+}
+
+
+impl Peppers {
+    /// Reads the legacy pepper from `PEPPER`, and the others from `PEPPERS`,
+    /// a comma separated list of `id=pepper` pairs with the newest last. IDs are at most 8 bytes.
+    pub fn from_env() -> crate::Result<Self> {
+        Self::parse(var("PEPPER").ok(), &var("PEPPERS").unwrap_or_default())
+    }
+
+    /// Parses `peppers` in the format of `PEPPERS`.
+    /// Every ID may only be used once, otherwise hashes with that ID could be checked against the wrong pepper.
+    fn parse(legacy: Option<String>, peppers: &str) -> crate::Result<Self> {
+        let mut parsed: Vec<(KeyId, String)> = Vec::new();
+        for pair in peppers.split(',').map(str::trim).filter(|pair|!pair.is_empty()) {
+            let (id, pepper) = pair.split_once('=').ok_or("PEPPERS has to be a list of id=pepper pairs")?;
+            let key_id = KeyId::new(id.as_bytes()).map_err(|_|format!("the pepper ID {id} is longer than {} bytes", KeyId::MAX_LEN))?;
+            if parsed.iter().any(|(parsed_id, _)|*parsed_id == key_id) {
+                return Err(format!("the pepper ID {id} is used more than once").into());
+            }
+            parsed.push((key_id, pepper.to_string()));
+        }
+        Ok(Self{legacy, peppers: parsed})
+    }
+
+    /// The ID and pepper new hashes are made with, `None` for the ID when only the legacy pepper is set.
+    pub fn current(&self) -> Option<(Option<KeyId>, &str)> {
+        match self.peppers.last() {
+            Some((id, pepper)) => Some((Some(*id), pepper)),
+            None => self.legacy.as_deref().map(|pepper|(None, pepper))
+        }
+    }
+
+    /// The pepper `hash` was made with, `None` if it was made without one.
+    /// Fails if the hash names a pepper that is not configured.
+    pub fn find(&self, hash: &PasswordHash) -> Result<Option<&str>, HashError> {
+        let params = Params::try_from(hash).map_err(|err|HashError::InvalidHash(err.to_string()))?;
+        if params.keyid().is_empty() {
+            return Ok(self.legacy.as_deref());
+        }
+        match self.peppers.iter().find(|(id, _)|id.as_bytes() == params.keyid()) {
+            Some((_, pepper)) => Ok(Some(pepper)),
+            None => Err(HashError::UnknownPepper)
+        }
+    }
+
+    /// An `Argon2` that verifies `hash` with the pepper it was made with.
+    /// Verification takes the algorithm, version and params from the hash, so only the pepper matters here.
+    pub fn verifier(&self, hash: &PasswordHash) -> Result<Argon2<'_>, HashError> {
+        match self.find(hash)? {
+            Some(pepper) => Argon2::new_with_secret(pepper.as_bytes(), Algorithm::default(), Version::default(), Params::default()).map_err(|err|HashError::Internal(err.to_string())),
+            None => Ok(Argon2::default())
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::{PasswordHasher, PasswordVerifier, ParamsBuilder};
+    use argon2::password_hash::SaltString;
+
+    fn argon2<'a>(id: Option<KeyId>, pepper: &'a str) -> Argon2<'a> {
+        let mut builder = ParamsBuilder::new();
+        builder.m_cost(Params::MIN_M_COST * 2).t_cost(1).p_cost(1);
+        if let Some(id) = id {
+            builder.keyid(id);
+        }
+        Argon2::new_with_secret(pepper.as_bytes(), Algorithm::default(), Version::default(), builder.build().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_with_the_pepper_of_the_hash() {
+        let (old, new) = (KeyId::new(b"2024").unwrap(), KeyId::new(b"2025").unwrap());
+        let peppers = Peppers{legacy: Some("legacy".into()), peppers: vec![(old, "old".into()), (new, "new".into())]};
+        assert_eq!(peppers.current(), Some((Some(new), "new")));
+        let salt = SaltString::from_b64("c2FsdHNhbHRzYWx0c2FsdA").unwrap();
+        for (id, pepper) in [(None, "legacy"), (Some(old), "old"), (Some(new), "new")] {
+            let hash = argon2(id, pepper).hash_password(b"password", &salt).unwrap();
+            assert!(peppers.verifier(&hash).unwrap().verify_password(b"password", &hash).is_ok());
+        }
+        let unknown = argon2(Some(KeyId::new(b"2023").unwrap()), "old").hash_password(b"password", &salt).unwrap();
+        assert_eq!(peppers.verifier(&unknown).err(), Some(HashError::UnknownPepper));
+    }
+
+    #[test]
+    fn test_parse() {
+        let peppers = Peppers::parse(None, "2024=old, 2025=new").unwrap();
+        assert_eq!(peppers.current(), Some((Some(KeyId::new(b"2025").unwrap()), "new")));
+        assert!(Peppers::parse(None, "2024=old,2025").is_err());
+        assert!(Peppers::parse(None, "toolongforanid=old").is_err());
+        assert!(Peppers::parse(None, "2024=old,2025=new,2024=newer").is_err());
+    }
+}
@@ -0,0 +1,8 @@
+use std::error::Error as StdError;
+
+mod argon;
+
+pub use argon::*;
+
+
+pub type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
@@ -0,0 +1,125 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use std::collections::HashMap;
+use chrono::{Utc, DateTime};
+use serde::Serialize;
+use shared::{Table, SortedIndex};
+use super::{Result, StdError};
+
+
+/// A change `keyrotator` made to the keys of a namespace.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AuditRecord {
+    pub id: String,
+    /// The namespace of the key parameters, which tells environments apart.
+    pub namespace: String,
+    pub time: DateTime<Utc>,
+    /// `Create`, `Update` or `Revoke`.
+    pub command: String,
+    /// The fingerprint of the key that signed tokens before the change.
+    pub old_kid: Option<String>,
+    /// The fingerprint of the key that signs tokens after the change.
+    pub new_kid: Option<String>,
+    /// Who or what invoked `keyrotator`, such as a schedule or an operator.
+    pub trigger: String,
+    pub reason: Option<String>
+}
+
+
+impl AuditRecord {
+    pub fn new(namespace: &str, command: &str, old_kid: Option<String>, new_kid: Option<String>, trigger: &str, reason: Option<String>) -> Self {
+        AuditRecord {
+            id: format!("{:x}", rand::random::<u128>()),
+            namespace: namespace.to_string(),
+            time: Utc::now(),
+            command: command.to_string(),
+            old_kid,
+            new_kid,
+            trigger: trigger.to_string(),
+            reason
+        }
+    }
+
+    /// The records of `namespace` between `from` and `until`, oldest first.
+    pub async fn history(client: &Client, namespace: &str, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<Self>> {
+        let from = AttributeValue::N(from.timestamp().to_string());
+        let until = AttributeValue::N(until.timestamp().to_string());
+        <AuditRecord as SortedIndex>::query_items(client, AttributeValue::S(namespace.to_string()), from, until).await
+    }
+}
+
+
+impl Table for AuditRecord {
+    type PK = AttributeValue;
+    type SK = AttributeValue;
+    type Error = Box<dyn StdError + Send + Sync>;
+    const NAME: &'static str = "Interphlix-Key-Audit";
+    const PK_NAME: &'static str = "id";
+    const SK_NAME: &'static str = "namespace";
+    const INDEX_NAME: &'static str = "NamespaceIndex";
+}
+
+
+impl SortedIndex for AuditRecord {
+    const RANGE_NAME: &'static str = "time";
+}
+
+
+impl From<AuditRecord> for HashMap<String, AttributeValue> {
+    fn from(record: AuditRecord) -> Self {
+        let mut map = HashMap::from([
+            (String::from("id"), AttributeValue::S(record.id)),
+            (String::from("namespace"), AttributeValue::S(record.namespace)),
+            (String::from("time"), AttributeValue::N(record.time.timestamp().to_string())),
+            (String::from("command"), AttributeValue::S(record.command)),
+            (String::from("trigger"), AttributeValue::S(record.trigger))
+        ]);
+        if let Some(old_kid) = record.old_kid {
+            map.insert(String::from("old_kid"), AttributeValue::S(old_kid));
+        }
+        if let Some(new_kid) = record.new_kid {
+            map.insert(String::from("new_kid"), AttributeValue::S(new_kid));
+        }
+        if let Some(reason) = record.reason {
+            map.insert(String::from("reason"), AttributeValue::S(reason));
+        }
+        map
+    }
+}
+
+
+impl TryFrom<HashMap<String, AttributeValue>> for AuditRecord {
+    type Error = Box<dyn StdError + Send + Sync>;
+
+    fn try_from(map: HashMap<String, AttributeValue>) -> std::result::Result<Self, Self::Error> {
+        let string = |key: &str| -> Option<String> {
+            map.get(key).and_then(|value|value.as_s().ok()).cloned()
+        };
+        let time = map.get("time").and_then(|value|value.as_n().ok()).ok_or("time is missing")?.parse()?;
+        Ok(AuditRecord {
+            id: string("id").ok_or("id is missing")?,
+            namespace: string("namespace").ok_or("namespace is missing")?,
+            time: DateTime::from_timestamp(time, 0).ok_or("time is out of range")?,
+            command: string("command").ok_or("command is missing")?,
+            old_kid: string("old_kid"),
+            new_kid: string("new_kid"),
+            trigger: string("trigger").ok_or("trigger is missing")?,
+            reason: string("reason")
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_record_round_trip() {
+        let mut record = AuditRecord::new("interphlix/authentication", "Revoke", Some("k4.pid.old".into()), Some("k4.pid.new".into()), "operator", Some("leaked".into()));
+        record.time = DateTime::from_timestamp(record.time.timestamp(), 0).unwrap();
+        let map: HashMap<String, AttributeValue> = record.clone().into();
+        assert_eq!(map.get("time"), Some(&AttributeValue::N(record.time.timestamp().to_string())));
+        assert_eq!(AuditRecord::try_from(map).unwrap(), record);
+    }
+}
@@ -1,78 +1,252 @@
 use lambda_runtime::{service_fn, run};
+use lambda_runtime::tracing::{self, error};
 use std::error::Error as StdError;
 use lambda_runtime::LambdaEvent;
-use ed25519_dalek::SigningKey;
-use chrono::{Utc, TimeDelta};
-use aws_sdk_ssm::Client;
+use ed25519_dalek::SigningKey as Ed25519Key;
+use chrono::{Utc, DateTime, TimeDelta};
 use serde::Deserialize;
 use rand::rngs::OsRng;
 use std::env::var;
+use report::{Output, Status, Plan};
+use audit::AuditRecord;
 use shared::*;
 
 
+mod report;
+mod audit;
+
+
 type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
 
-#[derive(Debug, Clone, Deserialize, Copy)]
+#[derive(Debug, Clone, Deserialize)]
 pub enum Command {
     Create,
     Update,
+    /// Replaces a compromised key right away and removes it from the keyring.
+    /// `kid` defaults to the current signing key.
+    Revoke {
+        #[serde(default)]
+        kid: Option<String>,
+        reason: String
+    },
+    /// Reports the stored keys without changing them.
+    Status,
+    /// Reports what `Update` would do without writing anything.
+    DryRun,
+    /// Lists the audit records of the namespace, by default those of the last 30 days.
+    Audit {
+        #[serde(default)]
+        from: Option<DateTime<Utc>>,
+        #[serde(default)]
+        until: Option<DateTime<Utc>>
+    },
+    /// Deletes the `paseto_keys` parameter, which still holds the signing key of the first deployments in plaintext.
+    /// Run it once `Create` has written the wrapped keys, it refuses to run before then.
+    DeleteLegacyKeys,
+}
+
+
+/// A command on its own, or along with who triggered it for the audit trail.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Event {
+    Command(Command),
+    Triggered {
+        command: Command,
+        trigger: String
+    }
+}
+
+
+/// Everything a command needs, built once per cold start.
+pub struct Config {
+    pub store: Store,
+    pub parameters: Parameters,
+    /// Wraps the signing key before it is stored.
+    pub kek: Kek,
+    /// DynamoDB client the audit trail is written with.
+    pub client: aws_sdk_dynamodb::Client
 }
 
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    tracing::init_default_subscriber();
     let config = aws_config::load_from_env().await;
-    let client = aws_sdk_ssm::Client::new(&config);
-    let handler = service_fn(|event|handler(event, &client));
+    let store = Store::from_env(aws_sdk_ssm::Client::new(&config))?;
+    let parameters = Parameters::from_env();
+    let kek = Kek::from_env()?;
+    let client = aws_sdk_dynamodb::Client::new(&config);
+    let config = Config{store, parameters, kek, client};
+    let handler = service_fn(|event|handler(event, &config));
     run(handler).await?;
     Ok(())
 }
 
 
-async fn handler(event: LambdaEvent<Command>, client: &Client) -> Result<()> {
-    match event.payload {
-        Command::Create => create(client).await,
-        Command::Update => update(client).await,
+async fn handler(event: LambdaEvent<Event>, config: &Config) -> Result<Output> {
+    let (command, trigger) = match event.payload {
+        Event::Command(command) => (command, String::from("unknown")),
+        Event::Triggered{command, trigger} => (command, trigger)
+    };
+    match command {
+        Command::Create => create(config, &trigger).await,
+        Command::Update => update(config, &trigger).await,
+        Command::Revoke{kid, reason} => revoke(config, &trigger, kid, reason).await,
+        Command::Status => status(config).await,
+        Command::DryRun => dry_run(config).await,
+        Command::Audit{from, until} => audit(config, from, until).await,
+        Command::DeleteLegacyKeys => delete_legacy_keys(config).await,
     }
 }
 
 
-async fn create(client: &Client) -> Result<()> {
+async fn create(config: &Config, trigger: &str) -> Result<Output> {
+    let previous_keys = Keys::get(&config.store, &config.parameters, &config.kek).await?;
     let keys = keys(None);
-    keys.put(client).await
+    keys.put(&config.store, &config.parameters, &config.kek).await?;
+    record(config, "Create", previous_keys.as_ref(), &keys, trigger, None).await;
+    Ok(Output::Done)
 }
 
 
-async fn update(client: &Client) -> Result<()> {
-    let previous_keys = match Keys::get(client).await {
-        Ok(Some(value)) => value,
-        _ => {
-            let keys = keys(None);
-            keys.put(client).await?;
-            keys
-        }
-    };
-    if previous_keys.expires > Utc::now() + TimeDelta::days(1) {
-        return  Ok(());
+async fn update(config: &Config, trigger: &str) -> Result<Output> {
+    let previous_keys = Keys::get(&config.store, &config.parameters, &config.kek).await?;
+    if let Some(keys) = next_keys(previous_keys.as_ref()) {
+        keys.put(&config.store, &config.parameters, &config.kek).await?;
+        record(config, "Update", previous_keys.as_ref(), &keys, trigger, None).await;
     }
-    let prev_public_key = Some(previous_keys.public_key);
-    let keys = keys(prev_public_key);
-    keys.put(client).await
+    Ok(Output::Done)
 }
 
 
+async fn revoke(config: &Config, trigger: &str, kid: Option<String>, reason: String) -> Result<Output> {
+    let previous_keys = Keys::get(&config.store, &config.parameters, &config.kek).await?.ok_or("the keys have not been created")?;
+    let kid = kid.unwrap_or_else(||previous_keys.signing_key.public().id());
+    let mut keys = keys(Some(&previous_keys));
+    if keys.keyring.revoke(&kid, reason.clone()).is_none() {
+        return Err(format!("{kid} is not in the keyring").into());
+    }
+    keys.put(&config.store, &config.parameters, &config.kek).await?;
+    let reason = format!("revoked {kid}: {reason}");
+    record(config, "Revoke", Some(&previous_keys), &keys, trigger, Some(reason)).await;
+    Ok(Output::Done)
+}
+
+
+async fn status(config: &Config) -> Result<Output> {
+    let keys = Keys::get(&config.store, &config.parameters, &config.kek).await?;
+    Ok(Output::Status(Status::from(keys.as_ref())))
+}
+
+
+async fn dry_run(config: &Config) -> Result<Output> {
+    let previous_keys = Keys::get(&config.store, &config.parameters, &config.kek).await?;
+    let next_keys = next_keys(previous_keys.as_ref());
+    Ok(Output::Plan(Plan::new(previous_keys.as_ref(), next_keys.as_ref())))
+}
+
+
+async fn audit(config: &Config, from: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<Output> {
+    let until = until.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or(until - TimeDelta::days(30));
+    let records = AuditRecord::history(&config.client, &config.parameters.namespace, from, until).await?;
+    Ok(Output::Audit(records))
+}
 
-fn keys(prev_public_key: Option<[u8; 32]>) -> Keys {
-    let (private_key, public_key) = generate_keys();
+
+async fn delete_legacy_keys(config: &Config) -> Result<Output> {
+    if Keys::get_signing(&config.store, &config.parameters, &config.kek).await?.is_none() {
+        return Err("the keys have not been created".into());
+    }
+    config.store.delete(&config.parameters.legacy_name()).await?;
+    Ok(Output::Done)
+}
+
+
+/// Appends the change from `previous_keys` to `keys` to the audit trail.
+/// The keys are already written by then, so a failed write is only logged. Failing the command would have Lambda retry it and change the keys again.
+async fn record(config: &Config, command: &str, previous_keys: Option<&Keys>, keys: &Keys, trigger: &str, reason: Option<String>) {
+    let old_kid = previous_keys.map(|keys|keys.signing_key.public().id());
+    let new_kid = Some(keys.signing_key.public().id());
+    let record = AuditRecord::new(&config.parameters.namespace, command, old_kid.clone(), new_kid.clone(), trigger, reason);
+    if let Err(err) = <AuditRecord as Table>::create_item(&config.client, record).await {
+        error!(command, old_kid, new_kid, trigger, "failed to write the audit record: {err}");
+    }
+}
+
+
+/// The keys `Update` writes, or `None` while the current key is not due for rotation.
+fn next_keys(previous_keys: Option<&Keys>) -> Option<Keys> {
+    match previous_keys {
+        Some(previous_keys) if previous_keys.signing_key.rotates_at() > Utc::now() => None,
+        previous_keys => Some(keys(previous_keys))
+    }
+}
+
+
+/// Generates new keys, keeping the keys of `previous_keys` in the keyring until they are past the retention window.
+fn keys(previous_keys: Option<&Keys>) -> Keys {
+    let key = Ed25519Key::generate(&mut OsRng);
     let expiry_days = var("EXPIRY_DAYS").unwrap_or("30".into()).parse().unwrap_or(30);
     let created_time = Utc::now();
     let expires = created_time + TimeDelta::days(expiry_days);
-    Keys{private_key, public_key, prev_public_key, created_time, expires}
+    let version = previous_keys.map(|keys|keys.signing_key.version + 1).unwrap_or(1);
+    let signing_key = SigningKey{version, private_key: key.to_bytes(), public_key: key.verifying_key().to_bytes(), created_time, expires};
+    let mut keyring = previous_keys.map(|keys|keys.keyring.clone()).unwrap_or_default();
+    if let Some(previous_keys) = previous_keys {
+        if keyring.find(&previous_keys.signing_key.public().id()).is_none() {
+            keyring.keys.insert(0, previous_keys.signing_key.public());
+        }
+    }
+    keyring.rotate(signing_key.public(), retention());
+    Keys{signing_key, keyring}
 }
 
 
-fn generate_keys() -> ([u8; 32], [u8; 32]) {
-    let keys = SigningKey::generate(&mut OsRng);
-    let keys = (keys.to_bytes(), keys.verifying_key().to_bytes());
-    keys
-}
\ No newline at end of file
+/// How long retired keys stay in the keyring.
+/// This has to cover the longest lifetime of an access token, so it reads the same settings as the authentication service:
+/// tokens live `TOKEN_MINUTES`, unless `MAX_TOKEN_AGE_MINUTES` lets verifiers accept them for longer, plus the leeway.
+fn retention() -> TimeDelta {
+    let token_minutes = var("TOKEN_MINUTES").unwrap_or("15".into()).parse().unwrap_or(15);
+    let max_token_age = var("MAX_TOKEN_AGE_MINUTES").ok().and_then(|minutes|minutes.parse().ok()).unwrap_or(0);
+    let leeway = var("LEEWAY_SECONDS").unwrap_or("30".into()).parse().unwrap_or(30);
+    TimeDelta::minutes(token_minutes.max(max_token_age)) + TimeDelta::seconds(leeway)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::config::BehaviorVersion;
+
+    #[tokio::test]
+    async fn test_revoke_when_the_audit_trail_fails() {
+        // The client has no region, so every audit write fails.
+        let client = aws_sdk_dynamodb::Client::from_conf(aws_sdk_dynamodb::Config::builder().behavior_version(BehaviorVersion::latest()).build());
+        let config = Config{store: Store::Memory(MemoryStore::default()), parameters: Parameters::default(), kek: Kek::new([9; 32]), client};
+        create(&config, "test").await.unwrap();
+        let created = Keys::get(&config.store, &config.parameters, &config.kek).await.unwrap().unwrap();
+        let kid = created.signing_key.public().id();
+
+        revoke(&config, "test", None, String::from("leaked")).await.unwrap();
+        let keys = Keys::get(&config.store, &config.parameters, &config.kek).await.unwrap().unwrap();
+        assert_eq!(keys.signing_key.version, 2);
+        assert!(keys.keyring.find(&kid).is_none());
+        assert!(keys.keyring.revoked.iter().any(|revoked|revoked.kid == kid));
+    }
+
+    #[tokio::test]
+    async fn test_delete_legacy_keys() {
+        let client = aws_sdk_dynamodb::Client::from_conf(aws_sdk_dynamodb::Config::builder().behavior_version(BehaviorVersion::latest()).build());
+        let config = Config{store: Store::Memory(MemoryStore::default()), parameters: Parameters::default(), kek: Kek::new([9; 32]), client};
+        let legacy_name = config.parameters.legacy_name();
+        config.store.put(&legacy_name, String::from("{}"), true).await.unwrap();
+        assert!(delete_legacy_keys(&config).await.is_err());
+        assert!(config.store.get(&legacy_name).await.unwrap().is_some());
+
+        create(&config, "test").await.unwrap();
+        delete_legacy_keys(&config).await.unwrap();
+        assert_eq!(config.store.get(&legacy_name).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,168 @@
+use shared::{Keys, PublicKey, RevokedKey};
+use super::audit::AuditRecord;
+use chrono::{Utc, DateTime};
+use serde::Serialize;
+
+
+/// What a command returns to whoever invoked the Lambda.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Output {
+    Done,
+    Status(Status),
+    Plan(Plan),
+    Audit(Vec<AuditRecord>)
+}
+
+
+/// A public key, identified by its PASERK `k4.pid` fingerprint.
+#[derive(Debug, Serialize)]
+pub struct KeyStatus {
+    pub kid: String,
+    pub version: u32,
+    pub created_time: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retired: Option<DateTime<Utc>>
+}
+
+
+/// The keys as they are stored in SSM.
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub signing_key: Option<KeyStatus>,
+    pub keyring: Vec<KeyStatus>,
+    pub revoked: Vec<RevokedKey>,
+    pub rotates_at: Option<DateTime<Utc>>,
+    /// Negative once the key is overdue.
+    pub rotates_in_seconds: Option<i64>
+}
+
+
+/// What `Update` would do right now.
+#[derive(Debug, Serialize)]
+pub enum Action {
+    /// There are no keys yet, so they would be created.
+    Create,
+    /// The current key is due, so it would be retired and the keys past the retention window dropped.
+    Rotate {
+        retires: String,
+        drops: Vec<String>
+    },
+    /// The current key is not due yet.
+    Nothing
+}
+
+
+/// The outcome of a dry run of `Update`.
+/// The key a rotation would create is generated for the report and then thrown away, so its fingerprint is only an example.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub action: Action,
+    pub keyring: Vec<KeyStatus>
+}
+
+
+impl From<&PublicKey> for KeyStatus {
+    fn from(key: &PublicKey) -> Self {
+        KeyStatus{kid: key.id(), version: key.version, created_time: key.created_time, expires: key.expires, retired: key.retired}
+    }
+}
+
+
+impl From<Option<&Keys>> for Status {
+    fn from(keys: Option<&Keys>) -> Self {
+        let keys = match keys {
+            Some(keys) => keys,
+            None => return Status{signing_key: None, keyring: Vec::new(), revoked: Vec::new(), rotates_at: None, rotates_in_seconds: None}
+        };
+        let rotates_at = keys.signing_key.rotates_at();
+        Status {
+            signing_key: Some(KeyStatus::from(&keys.signing_key.public())),
+            keyring: keys.keyring.keys.iter().map(KeyStatus::from).collect(),
+            revoked: keys.keyring.revoked.clone(),
+            rotates_at: Some(rotates_at),
+            rotates_in_seconds: Some((rotates_at - Utc::now()).num_seconds())
+        }
+    }
+}
+
+
+impl Plan {
+    /// Compares the stored keys with the keys `Update` would write, `next_keys` is `None` when it would write nothing.
+    pub fn new(previous_keys: Option<&Keys>, next_keys: Option<&Keys>) -> Self {
+        let action = match (previous_keys, next_keys) {
+            (None, _) => Action::Create,
+            (Some(_), None) => Action::Nothing,
+            (Some(previous_keys), Some(next_keys)) => Action::Rotate {
+                retires: previous_keys.signing_key.public().id(),
+                drops: previous_keys.keyring.keys.iter().map(PublicKey::id).filter(|kid|next_keys.keyring.find(kid).is_none()).collect()
+            }
+        };
+        let keyring = next_keys.or(previous_keys).map(|keys|keys.keyring.keys.iter().map(KeyStatus::from).collect()).unwrap_or_default();
+        Plan{action, keyring}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Config, dry_run};
+    use shared::{Store, MemoryStore, Parameters, Kek, Keyring, SigningKey};
+    use aws_sdk_dynamodb::config::BehaviorVersion;
+    use chrono::TimeDelta;
+
+    fn config(store: MemoryStore) -> Config {
+        let client = aws_sdk_dynamodb::Client::from_conf(aws_sdk_dynamodb::Config::builder().behavior_version(BehaviorVersion::latest()).build());
+        Config{store: Store::Memory(store), parameters: Parameters::default(), kek: Kek::new([9; 32]), client}
+    }
+
+    /// Stores a signing key that expires in `expires_in`, along with a key that was retired two days ago.
+    async fn stored(expires_in: TimeDelta) -> (MemoryStore, Keys) {
+        let store = MemoryStore::default();
+        let created_time = Utc::now() - TimeDelta::days(10);
+        let signing_key = SigningKey{version: 2, private_key: [2; 32], public_key: [2; 32], created_time, expires: Utc::now() + expires_in};
+        let mut retired = SigningKey{version: 1, private_key: [1; 32], public_key: [1; 32], created_time, expires: created_time}.public();
+        retired.retired = Some(Utc::now() - TimeDelta::days(2));
+        let keys = Keys{keyring: Keyring{keys: vec![signing_key.public(), retired], ..Default::default()}, signing_key};
+        keys.put(&store, &Parameters::default(), &Kek::new([9; 32])).await.unwrap();
+        (store, keys)
+    }
+
+    async fn plan(store: MemoryStore) -> Plan {
+        match dry_run(&config(store)).await.unwrap() {
+            Output::Plan(plan) => plan,
+            output => panic!("expected a plan, got {output:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_for_fresh_store() {
+        let plan = plan(MemoryStore::default()).await;
+        assert!(matches!(plan.action, Action::Create));
+        assert_eq!(plan.keyring.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plan_for_store_due_for_rotation() {
+        let (store, keys) = stored(TimeDelta::hours(12)).await;
+        let plan = plan(store).await;
+        match plan.action {
+            Action::Rotate{retires, drops} => {
+                assert_eq!(retires, keys.signing_key.public().id());
+                assert_eq!(drops, vec![keys.keyring.keys[1].id()]);
+            },
+            action => panic!("expected a rotation, got {action:?}")
+        }
+        assert_eq!(plan.keyring.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_plan_for_store_not_due() {
+        let (store, keys) = stored(TimeDelta::days(20)).await;
+        let plan = plan(store).await;
+        assert!(matches!(plan.action, Action::Nothing));
+        assert_eq!(plan.keyring.len(), keys.keyring.keys.len());
+    }
+}
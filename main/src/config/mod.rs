@@ -0,0 +1,121 @@
+use super::domain::services::hasher::PasswordHasher;
+use super::domain::services::backend::Backend;
+use super::domain::types::{Error, Mail, Audience, Value, Validation};
+use aws_sdk_dynamodb::Client;
+use chrono::{DateTime, Utc, TimeDelta};
+use shared::aws_sdk_ssm;
+use shared::{Keys, SigningKey, Keyring, KeyFooter, Store, Parameters, Kek, Verifier};
+use std::sync::{Arc, RwLock, PoisonError};
+use std::collections::HashMap;
+use std::env::var;
+use url::Url;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+/// Everything a request handler needs, built once per cold start.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// DynamoDB client used by the `Table`/`Manager` traits.
+    pub client: Client,
+    /// Caches the keyring, which it loads from the store picked with `KEY_STORE`.
+    pub verifier: Arc<Verifier>,
+    /// The names of the key parameters in the store.
+    pub parameters: Parameters,
+    /// The unwrapped signing key, until it has to be loaded again.
+    signing_key: Arc<RwLock<Option<CachedSigningKey>>>,
+    /// Unwraps the signing key, read from `KEY_ENCRYPTION_KEY`.
+    pub kek: Kek,
+    /// Client for the argon Lambda.
+    pub hasher: PasswordHasher,
+    /// SMTP mailer configured from the `MAIL` environment variable.
+    pub mail: Mail,
+    /// The page that handles magic links, they are sent as `VERIFY_URL?magic_id=...`.
+    pub verify_url: Url,
+    /// The `iss` claim of issued tokens.
+    pub issuer: String,
+    /// The `aud` claim of issued tokens, a comma separated list in the `AUDIENCE` environment variable.
+    pub audience: Audience,
+    /// Lifetime of access tokens in minutes.
+    pub token_minutes: i64,
+    /// Custom claims added to every access token, a JSON object in the `CLAIMS` environment variable.
+    pub claims: HashMap<String, Value>,
+    /// Whether users have to verify their email before they can login.
+    pub require_verified_email: bool,
+    /// The policy access tokens are checked against.
+    pub validation: Validation
+}
+
+
+#[derive(Debug, Clone)]
+struct CachedSigningKey {
+    signing_key: SigningKey,
+    expires: DateTime<Utc>
+}
+
+
+impl Config {
+    pub async fn new() -> crate::Result<Self> {
+        let config = aws_config::load_from_env().await;
+        let client = Client::new(&config);
+        let store = Store::from_env(aws_sdk_ssm::Client::new(&config)).map_err(|err| -> Box<dyn std::error::Error> { err })?;
+        let parameters = Parameters::from_env();
+        let kek = Kek::from_env().map_err(|err| -> Box<dyn std::error::Error> { err })?;
+        let backend = Backend::from_env(aws_sdk_lambda::Client::new(&config)).map_err(|err| -> Box<dyn std::error::Error> { err })?;
+        let hasher = PasswordHasher::new(backend);
+        let mail = serde_json::from_str(&var("MAIL")?)?;
+        let verify_url = var("VERIFY_URL")?.parse()?;
+        let issuer = var("ISSUER").unwrap_or(String::from("interphlix"));
+        let audience = var("AUDIENCE").unwrap_or(String::from("interphlix")).parse()?;
+        let token_minutes = var("TOKEN_MINUTES").unwrap_or("15".into()).parse().unwrap_or(15);
+        let claims = match var("CLAIMS") {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(_) => HashMap::new()
+        };
+        let require_verified_email = var("REQUIRE_VERIFIED_EMAIL").unwrap_or("true".into()).parse().unwrap_or(true);
+        let validation = Validation {
+            issuer: Some(issuer.clone()),
+            audiences: match &audience {
+                Audience::One(audience) => vec![audience.clone()],
+                Audience::Many(audiences) => audiences.clone()
+            },
+            leeway: TimeDelta::seconds(var("LEEWAY_SECONDS").unwrap_or("30".into()).parse().unwrap_or(30)),
+            max_age: var("MAX_TOKEN_AGE_MINUTES").ok().and_then(|minutes|minutes.parse().ok()).map(TimeDelta::minutes),
+            required_claims: claims.keys().cloned().collect()
+        };
+        let verifier = Arc::new(Verifier::new(store, parameters.clone(), validation.clone()));
+        let signing_key = Arc::default();
+        Ok(Self{client, verifier, parameters, signing_key, kek, hasher, mail, verify_url, issuer, audience, token_minutes, claims, require_verified_email, validation})
+    }
+
+    /// Loads the current PASETO signing key.
+    /// It is cached until `keyrotator` is due to replace it, but never longer than the verifier caches the keyring, so a revoked key is dropped as quickly.
+    pub async fn signing_key(&self) -> Result<SigningKey> {
+        let now = Utc::now();
+        if let Some(cached) = self.signing_key.read().unwrap_or_else(PoisonError::into_inner).as_ref() {
+            if now < cached.expires {
+                return Ok(cached.signing_key.clone());
+            }
+        }
+        let signing_key = match Keys::get_signing(self.verifier.store(), &self.parameters, &self.kek).await {
+            Ok(Some(signing_key)) => signing_key,
+            Ok(None) => return Err(Error::InternalServerError("the paseto signing key has not been created".into())),
+            Err(err) => return Err(Error::InternalServerError(err))
+        };
+        // Once rotation is due the key is still cached for a minute, rather than loaded for every token until keyrotator runs.
+        let expires = signing_key.rotates_at()
+            .min(now + TimeDelta::minutes(Verifier::MAX_AGE_MINUTES))
+            .max(now + TimeDelta::minutes(1));
+        let cached = CachedSigningKey{signing_key: signing_key.clone(), expires};
+        *self.signing_key.write().unwrap_or_else(PoisonError::into_inner) = Some(cached);
+        Ok(signing_key)
+    }
+
+    /// Loads the PASETO verification keys through the verifier's cache.
+    /// `token` is the token about to be verified, a key it names that the cached keyring does not know is fetched.
+    pub async fn keyring(&self, token: Option<&str>) -> Result<Keyring> {
+        let kid = token.and_then(KeyFooter::decode).map(|(_, key_footer)|key_footer.kid);
+        Ok(self.verifier.keyring(kid.as_deref()).await?)
+    }
+}
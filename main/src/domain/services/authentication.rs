@@ -0,0 +1,200 @@
+use super::super::types::{Error, Either, User, EmailAddress, Verification, Mail, Value};
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{AttributeValue, Put, Delete, TransactWriteItem};
+use super::verification::VerificationService;
+use super::hasher::PasswordHasher;
+use lettre::message::Mailbox;
+use lambda_http::tracing::error;
+use lettre::Address;
+use aws_sdk_dynamodb::Client;
+use super::manager::Manager;
+use std::collections::HashMap;
+use super::table::Table;
+use url::Url;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+const VERIFICATION_MAIL: &str = include_str!("mail.html");
+
+/// Holds an item per email, so a new user claims its email in the same write that stores the user.
+/// The `EmailIndex` of the users table can't keep two signups from using the same email.
+const EMAILS_TABLE: &str = "Interphlix-User-Emails";
+
+
+pub trait AuthenticationService: Sized {
+    /// Registers a new user.
+    /// Fails with `UserWithEmailAlreadyExists` if the email is taken.
+    /// Otherwise it hashes the password, stores the user with an unverified `EmailAddress::New`,
+    /// and mails the verification code and magic link to the user. Returns the stored user.
+    /// When the mail can't be sent the user is removed again, so signing up again sends a new code.
+    async fn signup(client: &Client, hasher: &PasswordHasher, mail: &Mail, verify_url: &Url, mut user: User) -> Result<User> {
+        let address = match user.email {
+            EmailAddress::New(address) | EmailAddress::Verified(address) => address
+        };
+        // Users from before emails were claimed only show up in the index.
+        if User::exists(client, Either::Left(EmailAddress::New(address.clone()))).await? {
+            return Err(Error::UserWithEmailAlreadyExists);
+        }
+        user.email = EmailAddress::New(address.clone());
+        user.password = hasher.hash(user.password).await?;
+        create_user(client, user.clone()).await?;
+        let sent = send_verification(client, mail, verify_url, &user, address).await;
+        // Only the message of the error is kept, the error itself can't be held while the user is deleted.
+        if let Some(err) = sent.err().map(|err|err.to_string()) {
+            delete_user(client, &user).await?;
+            return Err(Error::InternalServerError(err.into()));
+        }
+        Ok(user)
+    }
+
+    /// Checks the credentials of a user and returns the user.
+    /// Unknown emails and wrong passwords both fail with `WrongEmailOrPassword`, a corrupt stored hash with `InternalServerError`,
+    /// and accounts that still have an `EmailAddress::New` fail with `EmailNotVerified` when `require_verified` is set.
+    /// Passwords hashed with outdated argon params are hashed again and stored, failing to do so is logged but does not fail the login.
+    async fn login(client: &Client, hasher: &PasswordHasher, email: Address, password: String, require_verified: bool) -> Result<User> {
+        let mut user = match User::find(client, EmailAddress::New(email)).await? {
+            Some(user) => user,
+            None => return Err(Error::WrongEmailOrPassword)
+        };
+        let verified = hasher.verify(password.clone(), user.password.clone()).await?;
+        if require_verified && matches!(user.email, EmailAddress::New(_)) {
+            return Err(Error::EmailNotVerified);
+        }
+        if !verified.rehash {
+            return Ok(user);
+        }
+        let hash = match hasher.hash(password).await {
+            Ok(hash) => hash,
+            Err(err) => {
+                error!(user = %user.id.to_hex(), "failed to rehash the password: {err}");
+                return Ok(user);
+            }
+        };
+        let update = HashMap::from([(String::from("password"), Value::String(hash.clone()))]);
+        match User::update(client, user.id.clone(), update).await {
+            Ok(_) => user.password = hash,
+            Err(err) => error!(user = %user.id.to_hex(), "failed to store the rehashed password: {err}")
+        }
+        Ok(user)
+    }
+}
+
+
+impl AuthenticationService for User {}
+
+
+/// Stores `user` and claims its email in one transaction, failing with `UserWithEmailAlreadyExists` when the email is claimed already.
+async fn create_user(client: &Client, user: User) -> Result<()> {
+    let claim = Put::builder()
+        .table_name(EMAILS_TABLE)
+        .item("email", AttributeValue::from(user.email.clone()))
+        .item("user_id", user.id.clone().into())
+        .condition_expression("attribute_not_exists(email)")
+        .build()?;
+    let item = Put::builder()
+        .table_name(<User as Table>::NAME)
+        .set_item(Some(user.into()))
+        .condition_expression("attribute_not_exists(id)")
+        .build()?;
+    let output = client.transact_write_items()
+        .transact_items(TransactWriteItem::builder().put(claim).build())
+        .transact_items(TransactWriteItem::builder().put(item).build())
+        .send().await;
+    match output {
+        Ok(_) => Ok(()),
+        Err(err) => match err.as_service_error() {
+            Some(TransactWriteItemsError::TransactionCanceledException(canceled))
+                if canceled.cancellation_reasons().iter().any(|reason|reason.code() == Some("ConditionalCheckFailed")) => Err(Error::UserWithEmailAlreadyExists),
+            _ => Err(err.into())
+        }
+    }
+}
+
+/// Deletes `user` together with the claim on its email.
+async fn delete_user(client: &Client, user: &User) -> Result<()> {
+    let claim = Delete::builder()
+        .table_name(EMAILS_TABLE)
+        .key("email", AttributeValue::from(user.email.clone()))
+        .build()?;
+    let item = Delete::builder()
+        .table_name(<User as Table>::NAME)
+        .key(<User as Table>::PK_NAME, user.id.clone().into())
+        .key("email", AttributeValue::from(user.email.clone()))
+        .build()?;
+    client.transact_write_items()
+        .transact_items(TransactWriteItem::builder().delete(claim).build())
+        .transact_items(TransactWriteItem::builder().delete(item).build())
+        .send().await?;
+    Ok(())
+}
+
+/// Stores a new verification code for `user` and mails it with the magic link to `address`.
+async fn send_verification(client: &Client, mail: &Mail, verify_url: &Url, user: &User, address: Address) -> Result<()> {
+    let verification = Verification::generate_verification_code(client, user.id.clone()).await?;
+    let mut magic_link = verify_url.clone();
+    magic_link.query_pairs_mut().append_pair("magic_id", &verification.magic_id.simple().to_string());
+    let body = VERIFICATION_MAIL
+        .replace("{{magic_link}}", magic_link.as_str())
+        .replace("{{code}}", &format!("{:06}", verification.code));
+    let name = format!("{} {}", user.first_name, user.last_name);
+    let receiver = Mailbox::new(Some(name.trim().to_string()).filter(|name|!name.is_empty()), address);
+    mail.send_html_email(receiver, "Verify your email", body).await?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dynamo::Dynamo;
+    use super::super::super::types::Id;
+    #[cfg(feature = "local-hasher")]
+    use super::super::backend::{Backend, LocalBackend};
+    use chrono::Utc;
+
+    fn user() -> User {
+        User {
+            id: Id::new(),
+            email: EmailAddress::New("user@example.com".parse().unwrap()),
+            user_name: String::from("user"),
+            first_name: String::from("Test"),
+            last_name: String::from("User"),
+            password: String::from("hash"),
+            profile_picture: None,
+            created_at: Utc::now(),
+            expires: None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_user_claims_the_email() {
+        let dynamo = Dynamo::default();
+        create_user(&dynamo.client(), user()).await.unwrap();
+        let transaction = &dynamo.bodies("TransactWriteItems")[0];
+        assert!(transaction.contains(EMAILS_TABLE) && transaction.contains("attribute_not_exists(email)"));
+
+        // Another signup claimed the email between the check and the write.
+        let dynamo = Dynamo::default().respond("TransactWriteItems", 400, r#"{"__type": "com.amazonaws.dynamodb.v20120810#TransactionCanceledException", "Message": "Transaction cancelled", "CancellationReasons": [{"Code": "ConditionalCheckFailed"}, {"Code": "None"}]}"#);
+        assert!(matches!(create_user(&dynamo.client(), user()).await, Err(Error::UserWithEmailAlreadyExists)));
+    }
+
+    #[cfg(feature = "local-hasher")]
+    #[tokio::test]
+    async fn test_login_stores_the_rehashed_password() {
+        // An imported PBKDF2 hash of "password", which is always replaced by an argon2 hash.
+        let hash = "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA";
+        let item = format!(r#"{{"id": {{"S": "507f1f77bcf8"}}, "email": {{"S": "user@example.com"}}, "password": {{"S": "{hash}"}}, "created_at": {{"N": "1614000600000"}}}}"#);
+        let dynamo = Dynamo::default()
+            .respond("Query", 200, format!(r#"{{"Items": [{item}], "Count": 1}}"#))
+            .respond("UpdateItem", 200, format!(r#"{{"Attributes": {item}}}"#));
+        let hasher = PasswordHasher::new(Backend::Local(LocalBackend::from_env().unwrap()));
+
+        let user = User::login(&dynamo.client(), &hasher, "user@example.com".parse().unwrap(), "password".into(), false).await.unwrap();
+        assert!(user.password.starts_with("$argon2id$"));
+        assert_eq!(dynamo.operations(), ["Query", "Query", "UpdateItem"]);
+        let update = &dynamo.bodies("UpdateItem")[0];
+        assert!(update.contains(r##""#f0":"password""##) && update.contains("$argon2id$"));
+    }
+}
@@ -0,0 +1,142 @@
+use aws_sdk_lambda::types::InvocationType;
+use aws_sdk_lambda::primitives::Blob;
+use serde_json::{to_vec as json, from_slice};
+#[cfg(feature = "local-hasher")]
+use argon::{answer, new_argon2, Peppers};
+use shared::{AnyRequest, AnyResponse};
+#[cfg(feature = "local-hasher")]
+use std::fmt::{Debug, Formatter};
+#[cfg(feature = "local-hasher")]
+use tokio::task::spawn_blocking;
+use super::super::types::Error;
+use std::error::Error as StdError;
+use aws_sdk_lambda::Client;
+#[cfg(feature = "local-hasher")]
+use std::sync::Arc;
+use std::env::var;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+/// Something that answers argon requests.
+pub trait HashingBackend {
+    /// Answers `request`, `None` when the backend failed to handle it, such as an argon function that timed out or couldn't read it.
+    async fn send(&self, request: &AnyRequest) -> Result<Option<AnyResponse>>;
+}
+
+
+/// Invokes the argon function named in `ARGON`.
+#[derive(Debug, Clone)]
+pub struct LambdaBackend {
+    client: Client
+}
+
+
+/// Hashes in this process with the same configuration as the argon function, so nothing has to be deployed to hash passwords.
+/// Meant for running and testing the service offline, so it is only built with the `local-hasher` feature.
+#[cfg(feature = "local-hasher")]
+#[derive(Clone)]
+pub struct LocalBackend {
+    peppers: Arc<Peppers>
+}
+
+
+/// The backend picked through `HASHER`.
+#[derive(Debug, Clone)]
+pub enum Backend {
+    Lambda(LambdaBackend),
+    #[cfg(feature = "local-hasher")]
+    Local(LocalBackend)
+}
+
+
+impl LambdaBackend {
+    pub fn new(client: Client) -> Self {
+        Self{client}
+    }
+}
+
+
+impl HashingBackend for LambdaBackend {
+    async fn send(&self, request: &AnyRequest) -> Result<Option<AnyResponse>> {
+        let function_name = var("ARGON").unwrap_or(String::from("argon"));
+        let json = json(request).map_err(|err|Error::InternalServerError(Box::new(err)))?;
+        let payload = Blob::new(json);
+        let res = self.client.invoke()
+            .function_name(function_name)
+            .invocation_type(InvocationType::RequestResponse)
+            .payload(payload)
+            .send().await.map_err(|err|Error::InternalServerError(Box::new(err)))?;
+        if res.function_error.is_some() {
+            return Ok(None)
+        }
+        if let Some(payload) = res.payload {
+            let bytes = payload.into_inner();
+            let response: AnyResponse = from_slice(&bytes).map_err(|err|Error::InternalServerError(Box::new(err)))?;
+            return Ok(Some(response))
+        }
+        Err(Error::InternalServerError("internal server Error".into()))
+    }
+}
+
+
+#[cfg(feature = "local-hasher")]
+impl LocalBackend {
+    /// Reads the peppers and params from the same env vars as the argon function, failing when they are invalid.
+    pub fn from_env() -> argon::Result<Self> {
+        let peppers = Peppers::from_env()?;
+        new_argon2(&peppers)?;
+        Ok(Self{peppers: Arc::new(peppers)})
+    }
+}
+
+
+#[cfg(feature = "local-hasher")]
+impl Debug for LocalBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LocalBackend(..)")
+    }
+}
+
+
+#[cfg(feature = "local-hasher")]
+impl HashingBackend for LocalBackend {
+    async fn send(&self, request: &AnyRequest) -> Result<Option<AnyResponse>> {
+        let peppers = self.peppers.clone();
+        let request = request.clone();
+        // Hashing takes long enough to stall the other requests on this thread.
+        let response = spawn_blocking(move || -> argon::Result<AnyResponse> {
+            let argon2 = new_argon2(&peppers)?;
+            Ok(answer(&request, &argon2, &peppers))
+        }).await.map_err(|err|Error::InternalServerError(Box::new(err)))?;
+        let response = response.map_err(|err|Error::InternalServerError(err))?;
+        Ok(Some(response))
+    }
+}
+
+
+impl Backend {
+    /// Picks the backend named in `HASHER`: `lambda` (the default) or `local`, which needs the `local-hasher` feature.
+    pub fn from_env(client: Client) -> std::result::Result<Self, Box<dyn StdError + Send + Sync>> {
+        match var("HASHER").unwrap_or("lambda".into()).as_str() {
+            "lambda" => Ok(Backend::Lambda(LambdaBackend::new(client))),
+            #[cfg(feature = "local-hasher")]
+            "local" => Ok(Backend::Local(LocalBackend::from_env()?)),
+            #[cfg(not(feature = "local-hasher"))]
+            "local" => Err("the local hasher needs main to be built with the local-hasher feature".into()),
+            hasher => Err(format!("unknown hasher {hasher}").into())
+        }
+    }
+}
+
+
+impl HashingBackend for Backend {
+    async fn send(&self, request: &AnyRequest) -> Result<Option<AnyResponse>> {
+        match self {
+            Backend::Lambda(backend) => backend.send(request).await,
+            #[cfg(feature = "local-hasher")]
+            Backend::Local(backend) => backend.send(request).await
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use aws_sdk_dynamodb::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_dynamodb::config::http::{HttpRequest, HttpResponse};
+use aws_smithy_runtime_api::client::http::{HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector};
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::body::SdkBody;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use aws_sdk_dynamodb::Client;
+
+
+/// Answers DynamoDB requests with a canned response per operation and records the requests it was sent.
+/// Operations without a response are answered with an empty object.
+#[derive(Debug, Clone, Default)]
+pub struct Dynamo {
+    responses: HashMap<String, (u16, String)>,
+    requests: Arc<Mutex<Vec<(String, String)>>>
+}
+
+
+impl Dynamo {
+    /// Answers `operation` with `status` and `body`.
+    pub fn respond(mut self, operation: &str, status: u16, body: impl Into<String>) -> Self {
+        self.responses.insert(operation.to_string(), (status, body.into()));
+        self
+    }
+
+    /// A client that sends its requests to this connector.
+    pub fn client(&self) -> Client {
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("id", "secret", None, None, "test"))
+            .http_client(self.clone())
+            .build();
+        Client::from_conf(config)
+    }
+
+    /// The operations sent so far, in order.
+    pub fn operations(&self) -> Vec<String> {
+        self.requests.lock().unwrap().iter().map(|(operation, _)|operation.clone()).collect()
+    }
+
+    /// The bodies of the `operation` requests sent so far, in order.
+    pub fn bodies(&self, operation: &str) -> Vec<String> {
+        self.requests.lock().unwrap().iter().filter(|(sent, _)|sent == operation).map(|(_, body)|body.clone()).collect()
+    }
+}
+
+
+impl HttpConnector for Dynamo {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let operation = request.headers().get("x-amz-target").unwrap_or_default().trim_start_matches("DynamoDB_20120810.").to_string();
+        let body = String::from_utf8_lossy(request.body().bytes().unwrap_or_default()).into_owned();
+        let (status, response) = self.responses.get(&operation).cloned().unwrap_or((200, String::from("{}")));
+        self.requests.lock().unwrap().push((operation, body));
+        HttpConnectorFuture::ready(Ok(HttpResponse::new(status.try_into().unwrap(), SdkBody::from(response))))
+    }
+}
+
+
+impl HttpClient for Dynamo {
+    fn http_connector(&self, _: &HttpConnectorSettings, _: &RuntimeComponents) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
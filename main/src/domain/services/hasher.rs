@@ -1,61 +1,163 @@
-use aws_sdk_lambda::types::InvocationType;
-use aws_sdk_lambda::primitives::Blob;
-use serde_json::{to_vec as json, from_slice};
-use shared::{Request, Result as Response};
-use aws_sdk_lambda::Client;
-use std::env::var;
+use shared::{Request, AnyRequest, AnyResponse, VersionedRequest, Reply, Verified, HashError, Capability, MAX_BATCH};
+use super::backend::{Backend, HashingBackend};
+use std::sync::{Arc, Mutex, PoisonError};
+use super::super::types::Error;
 
 
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Clone)]
 pub struct PasswordHasher {
-    client: Client
+    backend: Backend,
+    /// What the argon function sent with its last response, `None` until it responded.
+    peer: Arc<Mutex<Option<Peer>>>
+}
+
+
+/// How the argon function last responded.
+#[derive(Debug, Clone)]
+struct Peer {
+    /// Whether it read a `VersionedRequest`, argon functions that predate versioning only read the bare request.
+    versioned: bool,
+    capabilities: Vec<Capability>
 }
 
 
 impl PasswordHasher {
-    pub async fn new() -> Self {
-        let config = aws_config::load_from_env().await;
-        let client = Client::new(&config);
-        Self{client}
+    pub fn new(backend: Backend) -> Self {
+        Self{backend, peer: Arc::default()}
     }
 
     pub async fn hash(&self, password: String) -> Result<String> {
-        let function_name = var("ARGON").unwrap_or(String::from("argon"));
-        let request = Request::Hash(password);
-        let json = json(&request)?;
-        let payload = Blob::new(json);
-        let res = self.client.invoke()
-            .function_name(function_name)
-            .invocation_type(InvocationType::RequestResponse)
-            .payload(payload)
-            .send().await?;
-        if let Some(payload) = res.payload {
-            let bytes = payload.into_inner();
-            let response: Response<String, String> = from_slice(&bytes)?;
-            let hash = std::result::Result::<String, String>::from(response)?;
-            return Ok(hash)
-        }
-        Err("internal server Error")?
-    }
-
-    pub async fn verify(&self, password: String, hash: String) -> Result<()> {
-        let function_name = var("ARGON").unwrap_or(String::from("Argon"));
-        let request = Request::Verify(password, hash);
-        let json = json(&request)?;
-        let payload = Blob::new(json);
-        let res = self.client.invoke()
-            .function_name(function_name)
-            .invocation_type(InvocationType::RequestResponse)
-            .payload(payload)
-            .send().await?;
-        if let Some(payload) = res.payload {
-            let bytes = payload.into_inner();
-            let response: Response<Option<()>, String> = from_slice(&bytes)?;
-            std::result::Result::<Option<()>, String>::from(response)?;
-            return Ok(())
+        hashed(self.invoke(Request::Hash(password)).await?)
+    }
+
+    /// Checks `password` against `hash`, `Verified::rehash` tells whether the hash is due for `hash` again.
+    /// A wrong password fails with `WrongEmailOrPassword`, a corrupt hash with `InternalServerError`.
+    pub async fn verify(&self, password: String, hash: String) -> Result<Verified> {
+        verified(self.invoke(Request::Verify(password, hash)).await?)
+    }
+
+    /// Hashes every password in as few invocations as possible, with a result for each password in the same order.
+    pub async fn hash_batch(&self, passwords: Vec<String>) -> Result<Vec<Result<String>>> {
+        let results = self.batch(passwords, Request::HashBatch, Request::Hash).await?;
+        Ok(results.into_iter().map(hashed).collect())
+    }
+
+    /// Verifies every password against its hash in as few invocations as possible, with a result for each pair in the same order.
+    pub async fn verify_batch(&self, items: Vec<(String, String)>) -> Result<Vec<Result<Verified>>> {
+        let results = self.batch(items, Request::VerifyBatch, |(password, hash)|Request::Verify(password, hash)).await?;
+        Ok(results.into_iter().map(verified).collect())
+    }
+
+    /// Whether the argon function said it can do `capability` when it last responded.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities().is_some_and(|capabilities|capabilities.contains(&capability))
+    }
+
+    fn capabilities(&self) -> Option<Vec<Capability>> {
+        self.peer().map(|peer|peer.capabilities)
+    }
+
+    fn peer(&self) -> Option<Peer> {
+        self.peer.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Sends `items` in `batch` requests of at most `MAX_BATCH` items, unless the argon function can not take batches, in which case each item is sent in a `single` request.
+    /// The capabilities are unknown until argon first responds, and argon functions that predate versioning fail on a batch, so a failed batch is then sent in single requests too.
+    /// Any other failure, such as a throttled invocation, fails the whole call.
+    async fn batch<T: Clone>(&self, items: Vec<T>, batch: fn(Vec<T>) -> Request, single: fn(T) -> Request) -> Result<Vec<std::result::Result<Reply, HashError>>> {
+        let mut results = Vec::with_capacity(items.len());
+        for chunk in items.chunks(MAX_BATCH) {
+            let capabilities = self.capabilities();
+            if capabilities.as_ref().is_none_or(|capabilities|capabilities.contains(&Capability::Batch)) {
+                let result = self.try_invoke(batch(chunk.to_vec())).await?;
+                match result {
+                    Some(Ok(Reply::Batch(replies))) => {
+                        results.extend(replies.into_iter().map(Into::into));
+                        continue;
+                    },
+                    Some(Ok(reply)) => return Err(Error::InternalServerError(format!("argon replied to a batch with {reply:?}").into())),
+                    Some(Err(HashError::Unsupported)) => {},
+                    Some(Err(err)) => return Err(err.into()),
+                    None if capabilities.is_none() => {},
+                    None => return Err(function_failed())
+                }
+            }
+            for item in chunk {
+                results.push(self.invoke(single(item.clone())).await?);
+            }
         }
-        Err("internal server Error")?
+        Ok(results)
     }
-}
\ No newline at end of file
+
+    async fn invoke(&self, request: Request) -> Result<std::result::Result<Reply, HashError>> {
+        self.try_invoke(request).await?.ok_or_else(function_failed)
+    }
+
+    /// Sends `request` in a `VersionedRequest`, or bare to an argon function that answered unversioned before, `None` when the function failed.
+    /// argon functions that predate versioning fail to read a `VersionedRequest`, so while it is unknown which one answers a failed invocation is retried with the bare request.
+    async fn try_invoke(&self, request: Request) -> Result<Option<std::result::Result<Reply, HashError>>> {
+        let peer = self.peer();
+        let response = match peer {
+            Some(Peer{versioned: false, ..}) => self.backend.send(&AnyRequest::Unversioned(request)).await?,
+            Some(Peer{versioned: true, ..}) => self.backend.send(&AnyRequest::Versioned(VersionedRequest::new(request))).await?,
+            None => {
+                let response = self.backend.send(&AnyRequest::Versioned(VersionedRequest::new(request.clone()))).await?;
+                match response {
+                    Some(response) => Some(response),
+                    None => self.backend.send(&AnyRequest::Unversioned(request)).await?
+                }
+            }
+        };
+        let Some(response) = response else {
+            return Ok(None)
+        };
+        let peer = Peer{versioned: matches!(response, AnyResponse::Versioned(_)), capabilities: response.capabilities()};
+        *self.peer.lock().unwrap_or_else(PoisonError::into_inner) = Some(peer);
+        Ok(Some(response.result()))
+    }
+}
+
+
+fn function_failed() -> Error {
+    Error::InternalServerError("the argon function failed".into())
+}
+
+
+fn hashed(result: std::result::Result<Reply, HashError>) -> Result<String> {
+    match result? {
+        Reply::Hash(hash) => Ok(hash),
+        reply => Err(Error::InternalServerError(format!("argon replied to a hash with {reply:?}").into()))
+    }
+}
+
+fn verified(result: std::result::Result<Reply, HashError>) -> Result<Verified> {
+    match result? {
+        Reply::Verified(verified) => Ok(verified),
+        reply => Err(Error::InternalServerError(format!("argon replied to a verification with {reply:?}").into()))
+    }
+}
+
+
+// The only backend that runs offline is the local one.
+#[cfg(all(test, feature = "local-hasher"))]
+mod tests {
+    use super::*;
+    use super::super::backend::LocalBackend;
+
+    #[tokio::test]
+    async fn test_local_backend() {
+        let hasher = PasswordHasher::new(Backend::Local(LocalBackend::from_env().unwrap()));
+        let hash = hasher.hash("password".into()).await.unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_eq!(hasher.verify("password".into(), hash.clone()).await.unwrap(), Verified{rehash: false});
+        assert!(matches!(hasher.verify("wrong".into(), hash.clone()).await, Err(Error::WrongEmailOrPassword)));
+        assert!(hasher.supports(Capability::Batch));
+        let results = hasher.verify_batch(vec![("password".into(), hash), ("password".into(), "corrupt".into())]).await.unwrap();
+        assert!(matches!(results[..], [Ok(Verified{rehash: false}), Err(Error::InternalServerError(_))]));
+        let results = hasher.verify_batch(vec![("password".into(), "corrupt".into()); MAX_BATCH + 1]).await.unwrap();
+        assert_eq!(results.len(), MAX_BATCH + 1);
+        assert!(results.iter().all(|result|matches!(result, Err(Error::InternalServerError(_)))));
+    }
+}
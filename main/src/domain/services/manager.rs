@@ -1,4 +1,4 @@
-use super::super::types::{Error, Value, Either, User, Verification};
+use super::super::types::{Error, Value, Either, User, Verification, RefreshToken, Revocation};
 use super::super::services::table::Table;
 use aws_sdk_dynamodb::operation::get_item;
 use chrono::{DateTime, Utc, TimeDelta};
@@ -10,7 +10,7 @@ type Result<T> = std::result::Result<T, Error>;
 
 /// The `Manager` trait provides a set of methods for managing items in a database.
 /// It extends the `Table` trait, which provides basic CRUD operations.
-pub trait Manager: Sized + Table {
+pub trait Manager: Sized + Table<Error = Error> {
     /// Checks if an item exists in the database.
     ///
     /// # Arguments
@@ -53,6 +53,21 @@ pub trait Manager: Sized + Table {
         <Self as Table>::get_item(client, key).await
     }
 
+    /// Reads an item from the database using its secondary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `key` - The secondary key of the item.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `Option` with the item if found, or `None` if not found.
+    async fn find(client: &Client, key: <Self as Table>::SK) -> Result<Option<Self>> {
+        let key = Either::Left(key);
+        <Self as Table>::get_item(client, key).await
+    }
+
     /// Updates an existing item in the database.
     ///
     /// # Arguments
@@ -104,4 +119,6 @@ pub trait Manager: Sized + Table {
 
 
 impl Manager for User {}
-impl Manager for Verification {}
\ No newline at end of file
+impl Manager for Verification {}
+impl Manager for RefreshToken {}
+impl Manager for Revocation {}
\ No newline at end of file
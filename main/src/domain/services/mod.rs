@@ -1,6 +1,12 @@
 #![allow(unused)]
+pub mod authentication;
 pub mod verification;
 pub mod manager;
-mod paseto;
-mod hasher;
-mod table;
\ No newline at end of file
+pub mod refresh;
+pub mod revocation;
+pub mod paseto;
+pub mod hasher;
+pub mod backend;
+mod table;
+#[cfg(test)]
+mod dynamo;
\ No newline at end of file
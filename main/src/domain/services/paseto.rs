@@ -1,56 +1,46 @@
 use chrono::Utc;
-use rusty_paseto::core::{ImplicitAssertion, PasetoError, Payload, Public, V4};
+use rusty_paseto::core::{PasetoError, Payload, Public, V4};
 use rusty_paseto::core::PasetoAsymmetricPrivateKey;
-use rusty_paseto::core::PasetoAsymmetricPublicKey;
 use rusty_paseto::core::Paseto as PasetoBuilder;
 use serde::{Serialize, de::DeserializeOwned};
-use super::super::types::{Error, Token};
+use super::super::types::{Error, Token, Validation};
 use rusty_paseto::core::Footer;
 use rusty_paseto::core::Key;
-use shared::Keys;
+use shared::{SigningKey, Keyring, KeyFooter};
+use zeroize::Zeroizing;
 
 
 type Result<T> = std::result::Result<T, Error>;
 pub trait Paseto: Serialize + DeserializeOwned + 'static {
     fn expired(&self) -> bool;
 
-    fn try_verify(signature: &str, keys: &Keys) -> Result<Self> {
-        let key = Key::from(&keys.public_key);
-        let public_key = From::from(&key);
-        let footer = Option::<Footer>::None;
-        let implicit_assertion = Option::<ImplicitAssertion>::None;
-        let json = match PasetoBuilder::try_verify(signature, &public_key, footer, implicit_assertion) {
+    /// Checks the claims of a token whose signature has already been verified.
+    fn validate(&self, validation: &Validation) -> Result<()>;
+
+    /// Verifies the signature of a token with the key named in its footer, then validates its claims against `validation`.
+    fn try_verify(signature: &str, keyring: &Keyring, validation: &Validation) -> Result<Self> {
+        let json = shared::verify(signature, keyring)?;
+        let token: Self = match serde_json::from_str(&json) {
             Ok(value) => value,
-            Err(err) => {
-                match err {
-                    PasetoError::InvalidSignature => {
-                        let key = match keys.prev_public_key {
-                            Some(key) => Key::from(key),
-                            None => Err(err)?
-                        };
-                        let public_key = From::from(&key);
-                        PasetoBuilder::try_verify(signature, &public_key, footer, implicit_assertion)?
-                    },
-                    _ => Err(err)?
-                }
-            }
+            Err(err) => return Err(Error::InvalidToken)
         };
-        match serde_json::from_str(&json) {
-            Ok(value) => Ok(value),
-            Err(err) => Err(Error::InvalidToken)
-        }
+        token.validate(validation)?;
+        Ok(token)
     }
 
 
-    fn try_sign(&self, keys: &Keys) -> Result<String> {
-        let mut key = [0u8; 64];
-        key[..32].copy_from_slice(&keys.private_key);
-        key[32..].copy_from_slice(&keys.public_key);
-        let key = Key::from(&key);
+    /// Signs the token with the current key and names that key in the footer.
+    fn try_sign(&self, signing_key: &SigningKey) -> Result<String> {
+        let mut key = Zeroizing::new([0u8; 64]);
+        key[..32].copy_from_slice(&signing_key.private_key);
+        key[32..].copy_from_slice(&signing_key.public_key);
+        let key = Key::from(&*key);
         let key = From::from(&key);
         let json = serde_json::to_string(&self).map_err(|err|Error::InternalServerError(err.into()))?;
         let payload = Payload::from(json.as_str());
-        let token = PasetoBuilder::<V4, Public>::builder().set_payload(payload).try_sign(&key)?;
+        let footer = KeyFooter::new(&signing_key.public()).encode();
+        let footer = Footer::from(footer.as_str());
+        let token = PasetoBuilder::<V4, Public>::builder().set_payload(payload).set_footer(footer).try_sign(&key)?;
         Ok(token)
     }
 }
@@ -63,4 +53,103 @@ impl Paseto for Token {
         }
         false
     }
-}
\ No newline at end of file
+
+    fn validate(&self, validation: &Validation) -> Result<()> {
+        Ok(validation.validate(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::types::{Id, Audience};
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use chrono::TimeDelta;
+    use std::collections::HashMap;
+
+    /// Builds a signing key from one of the RFC 8032 test key pairs.
+    fn signing_key(version: u32, private_key: &str, public_key: &str) -> SigningKey {
+        let bytes = |hex: &str| -> [u8; 32] {
+            let bytes: Vec<u8> = (0..hex.len()).step_by(2).map(|i|u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+            bytes.try_into().unwrap()
+        };
+        let created_time = Utc::now() - TimeDelta::days(1);
+        SigningKey{version, private_key: bytes(private_key), public_key: bytes(public_key), created_time, expires: created_time + TimeDelta::days(30)}
+    }
+
+    fn keys() -> (SigningKey, SigningKey) {
+        let old = signing_key(1, "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60", "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let new = signing_key(2, "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb", "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c");
+        (old, new)
+    }
+
+    fn token() -> Token {
+        Token::new(String::from("interphlix"), Id::new(), Audience::One(String::from("interphlix")), TimeDelta::minutes(15), HashMap::new())
+    }
+
+    /// Signs `token` the way tokens were signed before key ids, without a footer.
+    fn sign_without_footer(token: &Token, signing_key: &SigningKey) -> String {
+        let mut key = [0u8; 64];
+        key[..32].copy_from_slice(&signing_key.private_key);
+        key[32..].copy_from_slice(&signing_key.public_key);
+        let key = Key::from(&key);
+        let key = PasetoAsymmetricPrivateKey::<V4, Public>::from(&key);
+        let json = serde_json::to_string(token).unwrap();
+        PasetoBuilder::<V4, Public>::builder().set_payload(Payload::from(json.as_str())).try_sign(&key).unwrap()
+    }
+
+    #[test]
+    fn test_verify_with_a_retired_key() {
+        let (old, new) = keys();
+        let mut keyring = Keyring::default();
+        keyring.rotate(old.public(), TimeDelta::hours(1));
+        let signed = token().try_sign(&old).unwrap();
+        keyring.rotate(new.public(), TimeDelta::hours(1));
+        assert!(keyring.keys[1].retired.is_some());
+        let token = Token::try_verify(&signed, &keyring, &Validation::default()).unwrap();
+        assert_eq!(token.issuer, "interphlix");
+    }
+
+    #[test]
+    fn test_reject_unknown_and_revoked_keys() {
+        let (old, new) = keys();
+        let signed = token().try_sign(&old).unwrap();
+        let mut keyring = Keyring::default();
+        keyring.rotate(new.public(), TimeDelta::hours(1));
+        assert!(matches!(Token::try_verify(&signed, &keyring, &Validation::default()), Err(Error::InvalidToken)));
+
+        keyring.keys.push(old.public());
+        assert!(Token::try_verify(&signed, &keyring, &Validation::default()).is_ok());
+        keyring.revoke(&old.public().id(), String::from("leaked")).unwrap();
+        assert!(matches!(Token::try_verify(&signed, &keyring, &Validation::default()), Err(Error::InvalidToken)));
+    }
+
+    #[test]
+    fn test_verify_without_footer_with_the_current_key() {
+        let (old, new) = keys();
+        let mut keyring = Keyring::default();
+        keyring.rotate(old.public(), TimeDelta::hours(1));
+        keyring.rotate(new.public(), TimeDelta::hours(1));
+        let current = sign_without_footer(&token(), &new);
+        assert!(Token::try_verify(&current, &keyring, &Validation::default()).is_ok());
+        // Without a footer only the current key is tried, even though the retired key is still in the keyring.
+        let retired = sign_without_footer(&token(), &old);
+        assert!(matches!(Token::try_verify(&retired, &keyring, &Validation::default()), Err(Error::InvalidToken)));
+    }
+
+    #[test]
+    fn test_reject_tampered_footer() {
+        let (old, new) = keys();
+        let mut keyring = Keyring::default();
+        keyring.rotate(old.public(), TimeDelta::hours(1));
+        keyring.rotate(new.public(), TimeDelta::hours(1));
+        let signed = token().try_sign(&new).unwrap();
+        let (body, _) = signed.rsplit_once('.').unwrap();
+        // The same kid in a different encoding, and the kid of the other key in the keyring.
+        let footers = [format!(r#"{{"kid": "{}"}}"#, new.public().id()), KeyFooter::new(&old.public()).encode()];
+        for footer in footers {
+            let tampered = format!("{body}.{}", URL_SAFE_NO_PAD.encode(footer));
+            assert!(matches!(Token::try_verify(&tampered, &keyring, &Validation::default()), Err(Error::InvalidToken)));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use super::super::types::{RefreshToken, Error, Either, Id, Uuid};
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{TimeDelta, Utc};
+use aws_sdk_dynamodb::Client;
+use super::table::Table;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+pub trait RefreshTokenService {
+    const EXPIRY_DAYS: i64 = 30;
+
+    /// Starts a new refresh token family for `user_id` and saves it to the database, then returns it.
+    async fn generate_refresh_token(client: &Client, user_id: Id) -> Result<RefreshToken> {
+        let refresh_token = RefreshToken {
+            family: Uuid::new_v4(),
+            user_id,
+            token: Uuid::new_v4(),
+            expires: Utc::now() + TimeDelta::days(Self::EXPIRY_DAYS)
+        };
+        <RefreshToken as Table>::create_item(client, refresh_token.clone()).await?;
+        Ok(refresh_token)
+    }
+
+    /// Exchanges the refresh token in `secret` for the next token of its family.
+    /// If the family is unknown or has expired, it returns an error of InvalidToken.
+    /// If `secret` is an older token of the family, the token was reused, so the whole family is deleted
+    /// and an error of InvalidToken is returned.
+    /// The new token only replaces `secret` if `secret` is still the current token when it is written,
+    /// so of two concurrent refreshes with the same token, the second one counts as reuse too.
+    async fn rotate_refresh_token(client: &Client, secret: &str) -> Result<RefreshToken> {
+        let (family, token) = RefreshToken::parse(secret).ok_or(Error::InvalidToken)?;
+        let mut refresh_token = match <RefreshToken as Table>::get_item(client, Either::Right(family.clone())).await? {
+            Some(refresh_token) => refresh_token,
+            None => return Err(Error::InvalidToken)
+        };
+        if refresh_token.expires <= Utc::now() || refresh_token.token != token {
+            <RefreshToken as Table>::delete_item(client, family).await?;
+            return Err(Error::InvalidToken);
+        }
+        refresh_token.token = Uuid::new_v4();
+        let old = AttributeValue::S(token.simple().to_string());
+        let new = AttributeValue::S(refresh_token.token.simple().to_string());
+        if !<RefreshToken as Table>::swap_attribute(client, family.clone(), "token", old, new).await? {
+            <RefreshToken as Table>::delete_item(client, family).await?;
+            return Err(Error::InvalidToken);
+        }
+        Ok(refresh_token)
+    }
+
+    /// Deletes the family of the refresh token in `secret`, as long as it belongs to `user_id`.
+    async fn revoke_refresh_token(client: &Client, user_id: &Id, secret: &str) -> Result<()> {
+        let (family, _) = RefreshToken::parse(secret).ok_or(Error::InvalidToken)?;
+        let refresh_token = <RefreshToken as Table>::get_item(client, Either::Right(family.clone())).await?;
+        match refresh_token {
+            Some(refresh_token) if refresh_token.user_id == *user_id => <RefreshToken as Table>::delete_item(client, family).await,
+            _ => Err(Error::InvalidToken)
+        }
+    }
+}
+
+
+impl RefreshTokenService for RefreshToken {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dynamo::Dynamo;
+
+    #[tokio::test]
+    async fn test_rotate_refresh_token_lost_race_is_reuse() {
+        let family = Uuid::new_v4();
+        let token = Uuid::new_v4();
+        let expires = (Utc::now() + TimeDelta::days(1)).timestamp();
+        let item = format!(r#"{{"family": {{"S": "{}"}}, "user_id": {{"S": "507f1f77bcf8"}}, "token": {{"S": "{}"}}, "expires": {{"N": "{expires}"}}}}"#, family.simple(), token.simple());
+        let dynamo = Dynamo::default()
+            .respond("GetItem", 200, format!(r#"{{"Item": {item}}}"#))
+            .respond("UpdateItem", 400, r#"{"__type": "com.amazonaws.dynamodb.v20120810#ConditionalCheckFailedException", "message": "The conditional request failed"}"#);
+        let client = dynamo.client();
+
+        // The token is current when it is read, but another refresh replaces it before the update.
+        let secret = format!("{}.{}", family.simple(), token.simple());
+        let result = RefreshToken::rotate_refresh_token(&client, &secret).await;
+        assert!(matches!(result, Err(Error::InvalidToken)));
+        assert_eq!(dynamo.operations(), ["GetItem", "UpdateItem", "DeleteItem"]);
+    }
+}
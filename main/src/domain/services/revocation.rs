@@ -0,0 +1,34 @@
+use super::super::types::{Revocation, Error, Either, Token, Validation};
+use aws_sdk_dynamodb::Client;
+use super::paseto::Paseto;
+use super::table::Table;
+use shared::Keyring;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+pub trait RevocationService {
+    /// Revokes `token` until it expires.
+    async fn revoke_token(client: &Client, token: &Token) -> Result<()> {
+        <Revocation as Table>::create_item(client, Revocation::from(token)).await
+    }
+
+    /// Checks whether `token` has been revoked by its `jti`.
+    async fn is_revoked(client: &Client, token: &Token) -> Result<bool> {
+        <Revocation as Table>::item_exists(client, Either::Right(token.id.clone())).await
+    }
+
+    /// Verifies and validates `signature` like `Paseto::try_verify`,
+    /// then returns an error of InvalidToken if the token has been revoked.
+    async fn verify_token(client: &Client, signature: &str, keyring: &Keyring, validation: &Validation) -> Result<Token> {
+        let token = <Token as Paseto>::try_verify(signature, keyring, validation)?;
+        if Self::is_revoked(client, &token).await? {
+            return Err(Error::InvalidToken);
+        }
+        Ok(token)
+    }
+}
+
+
+impl RevocationService for Revocation {}
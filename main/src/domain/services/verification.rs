@@ -76,4 +76,33 @@ pub trait VerificationService {
 
 
 
-impl VerificationService for Verification {}
\ No newline at end of file
+impl VerificationService for Verification {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dynamo::Dynamo;
+    use super::super::super::types::EmailAddress;
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    #[tokio::test]
+    async fn test_verify_email_with_code() {
+        let expires = (Utc::now() + TimeDelta::minutes(5)).timestamp();
+        let verification = format!(r#"{{"user_id": {{"S": "507f1f77bcf8"}}, "magic_id": {{"S": "{}"}}, "code": {{"N": "123456"}}, "expires": {{"N": "{expires}"}}}}"#, Uuid::new_v4().simple());
+        let user = r#"{"id": {"S": "507f1f77bcf8"}, "email": {"S": "user@example.com"}, "password": {"S": "hash"}, "created_at": {"N": "1614000600000"}"#;
+        let dynamo = Dynamo::default()
+            .respond("GetItem", 200, format!(r#"{{"Item": {verification}}}"#))
+            .respond("Query", 200, format!(r#"{{"Items": [{user}}}], "Count": 1}}"#))
+            .respond("UpdateItem", 200, format!(r#"{{"Attributes": {user}, "email_verified": {{"BOOL": true}}}}}}"#));
+        let client = dynamo.client();
+
+        let user_id = Id::try_from(AttributeValue::S("507f1f77bcf8".into())).unwrap();
+        let user = Verification::verify_email(&client, Either::Left((user_id, 123456))).await.unwrap();
+        assert!(matches!(user.email, EmailAddress::Verified(_)));
+        assert_eq!(dynamo.operations(), ["GetItem", "Query", "UpdateItem"]);
+        let update = &dynamo.bodies("UpdateItem")[0];
+        assert!(!update.contains("AttributeUpdates"));
+        assert!(update.contains(r#""UpdateExpression":"SET #f0 = :v0""#));
+        assert!(update.contains(r#""ConditionExpression":"attribute_exists(#pk)""#));
+    }
+}
@@ -1,4 +0,0 @@
-pub enum Either<R, L> {
-    Right(R),
-    Left(L)
-}
\ No newline at end of file
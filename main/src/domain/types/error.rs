@@ -1,8 +1,10 @@
 use aws_sdk_dynamodb::operation::batch_get_item::BatchGetItemError;
 use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
 use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
 use aws_sdk_dynamodb::operation::put_item::PutItemError;
 use aws_sdk_dynamodb::operation::get_item::GetItemError;
+use aws_sdk_dynamodb::operation::query::QueryError;
 use lambda_http::http::header::CONTENT_TYPE;
 use lambda_http::http::header::HeaderValue;
 use std::fmt::{Display, Formatter, Debug};
@@ -10,6 +12,7 @@ use aws_sdk_dynamodb::error::BuildError;
 use std::error::Error as StdErrorTrait;
 use aws_sdk_config::error::SdkError;
 use rusty_paseto::core::PasetoError;
+use shared::{VerifyError, HashError, ItemNotFound};
 use lambda_http::http::StatusCode;
 use lambda_http::Response;
 use lambda_http::Body;
@@ -24,6 +27,11 @@ pub enum Error {
     VerificationCodeExpired,
     WrongVerificationCode,
     InvalidToken,
+    TokenExpired,
+    TokenNotYetValid,
+    WrongAudience,
+    WrongEmailOrPassword,
+    EmailNotVerified,
     InternalServerError(StdError),
     Custom(StatusCode, String, StdError)
 }
@@ -39,6 +47,11 @@ impl Error {
             VerificationCodeExpired => (StatusCode::GONE, String::from("the verification-code has expired")),
             WrongVerificationCode => (StatusCode::BAD_REQUEST, String::from("wrong verification code")),
             InvalidToken => (StatusCode::UNAUTHORIZED, String::from("invalid authorization token")),
+            TokenExpired => (StatusCode::UNAUTHORIZED, String::from("the authorization token has expired")),
+            TokenNotYetValid => (StatusCode::UNAUTHORIZED, String::from("the authorization token is not valid yet")),
+            WrongAudience => (StatusCode::UNAUTHORIZED, String::from("the authorization token is not meant for this service")),
+            WrongEmailOrPassword => (StatusCode::UNAUTHORIZED, String::from("wrong email or password")),
+            EmailNotVerified => (StatusCode::FORBIDDEN, String::from("the email address has not been verified")),
             InternalServerError(_) => (StatusCode::INTERNAL_SERVER_ERROR, String::from("internal server error. We are working on resolving the problem")),
             Custom(status, msg, _) => (*status, msg.clone())
         }
@@ -55,6 +68,11 @@ impl Display for Error {
             Error::VerificationCodeExpired => write!(f, "verification code has expired"),
             Error::WrongVerificationCode => write!(f, "wrong verification code"),
             Error::InvalidToken => write!(f, "invalid authorization token"),
+            Error::TokenExpired => write!(f, "token expired"),
+            Error::TokenNotYetValid => write!(f, "token not valid yet"),
+            Error::WrongAudience => write!(f, "token has the wrong audience"),
+            Error::WrongEmailOrPassword => write!(f, "wrong email or password"),
+            Error::EmailNotVerified => write!(f, "email not verified"),
             Error::InternalServerError(err) => write!(f, "{err}"),
             Error::Custom(status, _, err) => write!(f, "{err}"),
         }
@@ -89,6 +107,13 @@ impl From<GetItemError> for Error {
 }
 
 
+impl From<QueryError> for Error {
+    fn from(value: QueryError) -> Self {
+        Error::InternalServerError(Box::new(value))
+    }
+}
+
+
 impl From<StdError> for Error {
     fn from(value: StdError) -> Self {
         Error::InternalServerError(value)
@@ -120,6 +145,13 @@ impl From<UpdateItemError> for Error {
 }
 
 
+impl From<ItemNotFound> for Error {
+    fn from(err: ItemNotFound) -> Self {
+        Error::Custom(StatusCode::NOT_FOUND, String::from("item not found"), err.into())
+    }
+}
+
+
 impl From<DeleteItemError> for Error {
     fn from(value: DeleteItemError) -> Self {
         Error::InternalServerError(Box::new(value))
@@ -127,10 +159,17 @@ impl From<DeleteItemError> for Error {
 }
 
 
+impl From<TransactWriteItemsError> for Error {
+    fn from(value: TransactWriteItemsError) -> Self {
+        Error::InternalServerError(Box::new(value))
+    }
+}
+
+
 impl From<Error> for Response<Body> {
     fn from(err: Error) -> Self {
         let (status, msg) = err.as_json();
-        let body  = Body::Text(format!("{{\"msg\": \"{}\"}}", msg));
+        let body = Body::Text(serde_json::json!({"msg": msg}).to_string());
         let mut res = Response::new(body);
         res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         *res.status_mut() = status;
@@ -146,4 +185,45 @@ impl From<PasetoError> for Error {
             _ => Error::InternalServerError(err.into())
         }
     }
-}
\ No newline at end of file
+}
+
+
+impl From<VerifyError> for Error {
+    fn from(err: VerifyError) -> Self {
+        match err {
+            VerifyError::InvalidToken => Error::InvalidToken,
+            VerifyError::TokenExpired => Error::TokenExpired,
+            VerifyError::TokenNotYetValid => Error::TokenNotYetValid,
+            VerifyError::WrongAudience => Error::WrongAudience,
+            VerifyError::KeyStore(err) => Error::InternalServerError(err)
+        }
+    }
+}
+
+
+impl From<HashError> for Error {
+    fn from(err: HashError) -> Self {
+        match err {
+            HashError::WrongPassword => Error::WrongEmailOrPassword,
+            err => Error::InternalServerError(Box::new(err))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_response_is_json() {
+        let err = Error::Custom(StatusCode::BAD_REQUEST, String::from(r#"expected "a" or "b""#), "custom".into());
+        let res = Response::from(err);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = match res.body() {
+            Body::Text(text) => serde_json::from_str(text).unwrap(),
+            body => panic!("unexpected {body:?}")
+        };
+        assert_eq!(body["msg"], r#"expected "a" or "b""#);
+    }
+}
@@ -10,6 +10,12 @@ use crate::{Result, StdError};
 pub struct Id(ObjectId);
 
 
+impl Id {
+    pub fn new() -> Self {
+        Self(ObjectId::new())
+    }
+}
+
 
 impl Deref for Id {
     type Target = ObjectId;
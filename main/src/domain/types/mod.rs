@@ -2,7 +2,8 @@
 mod oauthprovider;
 mod verification;
 mod number;
-mod either;
+mod refresh;
+mod revocation;
 mod token;
 mod value;
 mod error;
@@ -15,11 +16,15 @@ mod id;
 pub use oauthprovider::*;
 pub use verification::*;
 pub use number::*;
-pub use either::*;
+pub use refresh::*;
+pub use revocation::*;
 pub use token::*;
 pub use value::*;
 pub use error::*;
 pub use uuid::*;
 pub use mail::*;
 pub use user::*;
-pub use id::*;
\ No newline at end of file
+pub use id::*;
+
+
+pub use shared::{Audience, Validation, VerifyError, Either};
\ No newline at end of file
@@ -0,0 +1,117 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::error::Error as StdError;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use std::convert::TryFrom;
+use super::{Id, Uuid};
+
+
+/// A family of refresh tokens created by one login.
+/// Every refresh rotates `token`, so only the latest token of a family is ever valid.
+/// Clients hold `family.token` as their refresh token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshToken {
+    pub family: Uuid,
+    pub user_id: Id,
+    pub token: Uuid,
+    pub expires: DateTime<Utc>
+}
+
+
+impl RefreshToken {
+    /// The opaque refresh token handed to the client.
+    pub fn secret(&self) -> String {
+        format!("{}.{}", self.family.simple(), self.token.simple())
+    }
+
+    /// Splits a refresh token from `secret` into its family and token.
+    pub fn parse(secret: &str) -> Option<(Uuid, Uuid)> {
+        let (family, token) = secret.split_once('.')?;
+        Some((family.parse().ok()?, token.parse().ok()?))
+    }
+}
+
+
+impl From<RefreshToken> for HashMap<String, AttributeValue> {
+    fn from(refresh_token: RefreshToken) -> Self {
+        let mut map = HashMap::new();
+        map.insert("family".to_string(), refresh_token.family.into());
+        map.insert("user_id".to_string(), refresh_token.user_id.into());
+        map.insert("token".to_string(), AttributeValue::S(refresh_token.token.simple().to_string()));
+        map.insert("expires".to_string(), AttributeValue::N(refresh_token.expires.timestamp().to_string()));
+        map
+    }
+}
+
+
+impl TryFrom<HashMap<String, AttributeValue>> for RefreshToken {
+    type Error = Box<dyn StdError>;
+
+    fn try_from(mut map: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let family = match map.remove("family") {
+            Some(value) => value.try_into()?,
+            _ => return Err("family not found or invalid".into()),
+        };
+
+        let user_id = match map.remove("user_id") {
+            Some(value) => value.try_into()?,
+            _ => return Err("user_id not found or invalid".into()),
+        };
+
+        let token = match map.remove("token") {
+            Some(value) => value.try_into()?,
+            _ => return Err("token not found or invalid".into()),
+        };
+
+        let expires = match map.remove("expires") {
+            Some(AttributeValue::N(s)) => {
+                let secs = s.parse::<i64>().map_err(|_| "invalid timestamp for field expires")?;
+                DateTime::from_timestamp(secs, 0).ok_or("invalid timestamp for field expires")?
+            },
+            _ => return Err("expires not found or invalid".into()),
+        };
+
+        Ok(RefreshToken {
+            family,
+            user_id,
+            token,
+            expires,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_refresh_token_hashmap_round_trip() {
+        let refresh_token = RefreshToken {
+            family: Uuid::new_v4(),
+            user_id: Id::from_str("507f1f77bcf86cd799439011").unwrap(),
+            token: Uuid::new_v4(),
+            expires: DateTime::from_timestamp(1_614_000_600, 0).unwrap(),
+        };
+
+        let map: HashMap<String, AttributeValue> = refresh_token.clone().into();
+        assert_eq!(map.get("expires").unwrap().as_n().unwrap(), "1614000600");
+        assert_eq!(RefreshToken::try_from(map).unwrap(), refresh_token);
+    }
+
+    #[test]
+    fn test_refresh_token_secret() {
+        let refresh_token = RefreshToken {
+            family: Uuid::new_v4(),
+            user_id: Id::default(),
+            token: Uuid::new_v4(),
+            expires: Utc::now(),
+        };
+
+        let (family, token) = RefreshToken::parse(&refresh_token.secret()).unwrap();
+        assert_eq!(family, refresh_token.family);
+        assert_eq!(token, refresh_token.token);
+        assert!(RefreshToken::parse("not-a-refresh-token").is_none());
+    }
+}
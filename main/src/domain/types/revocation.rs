@@ -0,0 +1,104 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::error::Error as StdError;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use std::convert::TryFrom;
+use super::{Id, Uuid, Token};
+
+
+/// A revoked access token, identified by its `jti`.
+/// It is kept until the token would have expired anyway.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revocation {
+    pub id: Uuid,
+    pub user_id: Id,
+    pub expires: Option<DateTime<Utc>>
+}
+
+
+impl<T> From<&Token<T>> for Revocation {
+    fn from(token: &Token<T>) -> Self {
+        Revocation {
+            id: token.id.clone(),
+            user_id: token.subject.clone(),
+            expires: token.expiration
+        }
+    }
+}
+
+
+impl From<Revocation> for HashMap<String, AttributeValue> {
+    fn from(revocation: Revocation) -> Self {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), revocation.id.into());
+        map.insert("user_id".to_string(), revocation.user_id.into());
+        if let Some(expires) = revocation.expires {
+            map.insert("expires".to_string(), AttributeValue::N(expires.timestamp().to_string()));
+        }
+        map
+    }
+}
+
+
+impl TryFrom<HashMap<String, AttributeValue>> for Revocation {
+    type Error = Box<dyn StdError>;
+
+    fn try_from(mut map: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let id = match map.remove("id") {
+            Some(value) => value.try_into()?,
+            _ => return Err("id not found or invalid".into()),
+        };
+
+        let user_id = match map.remove("user_id") {
+            Some(value) => value.try_into()?,
+            _ => return Err("user_id not found or invalid".into()),
+        };
+
+        let expires = match map.remove("expires") {
+            Some(AttributeValue::N(s)) => {
+                let secs = s.parse::<i64>().map_err(|_| "invalid timestamp for field expires")?;
+                Some(DateTime::from_timestamp(secs, 0).ok_or("invalid timestamp for field expires")?)
+            },
+            None => None,
+            _ => return Err("expires is invalid".into()),
+        };
+
+        Ok(Revocation {
+            id,
+            user_id,
+            expires,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revocation_hashmap_round_trip() {
+        let revocation = Revocation {
+            id: Uuid::new_v4(),
+            user_id: Id::default(),
+            expires: DateTime::from_timestamp(1_614_000_600, 0),
+        };
+
+        let map: HashMap<String, AttributeValue> = revocation.clone().into();
+        assert_eq!(map.get("expires").unwrap().as_n().unwrap(), "1614000600");
+        assert_eq!(Revocation::try_from(map).unwrap(), revocation);
+    }
+
+    #[test]
+    fn test_revocation_without_expiry() {
+        let revocation = Revocation {
+            id: Uuid::new_v4(),
+            user_id: Id::default(),
+            expires: None,
+        };
+
+        let map: HashMap<String, AttributeValue> = revocation.clone().into();
+        assert!(!map.contains_key("expires"));
+        assert_eq!(Revocation::try_from(map).unwrap(), revocation);
+    }
+}
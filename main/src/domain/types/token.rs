@@ -1,15 +1,8 @@
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use super::{Uuid, Id, Value};
-use chrono::{Utc, DateTime};
-
-
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-#[serde(untagged)]
-pub enum Audience {
-    One(String),
-    Many(Vec<String>)
-}
+use super::{Uuid, Id, Value, Audience};
+use shared::Claims;
+use chrono::{Utc, DateTime, TimeDelta};
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -33,11 +26,72 @@ pub struct Token<T = HashMap<String, Value>> {
 }
 
 
-impl Audience {
-    pub fn is_empty(&self) -> bool {
-        match self {
-            Audience::One(aud) => aud.is_empty(),
-            Audience::Many(aud) => aud.is_empty()
+impl<T> Token<T> {
+    /// Creates a token for `subject` that is valid from now until `lifetime` has passed.
+    pub fn new(issuer: String, subject: Id, audience: Audience, lifetime: TimeDelta, claims: T) -> Self {
+        let issued_at = Utc::now();
+        Token {
+            id: Uuid::new_v4(),
+            issuer,
+            subject,
+            audience,
+            expiration: Some(issued_at + lifetime),
+            not_before: Some(issued_at),
+            issued_at,
+            claims
         }
     }
+}
+
+
+impl Claims for Token {
+    fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    fn audience(&self) -> &Audience {
+        &self.audience
+    }
+
+    fn expiration(&self) -> Option<DateTime<Utc>> {
+        self.expiration
+    }
+
+    fn not_before(&self) -> Option<DateTime<Utc>> {
+        self.not_before
+    }
+
+    fn issued_at(&self) -> DateTime<Utc> {
+        self.issued_at
+    }
+
+    fn has_claim(&self, name: &str) -> bool {
+        self.claims.get(name).is_some_and(|claim|!matches!(claim, Value::None))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_token_claims() {
+        let lifetime = TimeDelta::minutes(15);
+        let token = Token::new("interphlix".into(), Id::default(), Audience::One("web".into()), lifetime, HashMap::<String, Value>::new());
+        assert_eq!(token.expiration, Some(token.issued_at + lifetime));
+        assert_eq!(token.not_before, Some(token.issued_at));
+        let json = serde_json::to_value(&token).unwrap();
+        assert_eq!(json["iss"], "interphlix");
+        assert_eq!(json["aud"], "web");
+    }
+
+    #[test]
+    fn test_has_claim() {
+        let claims = HashMap::from([(String::from("role"), Value::String("admin".into())), (String::from("tenant"), Value::None)]);
+        let token = Token::new("interphlix".into(), Id::default(), Audience::One("web".into()), TimeDelta::minutes(15), claims);
+        assert!(token.has_claim("role"));
+        assert!(!token.has_claim("tenant"));
+        assert!(!token.has_claim("plan"));
+    }
 }
\ No newline at end of file
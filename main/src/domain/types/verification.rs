@@ -46,7 +46,7 @@ impl TryFrom<HashMap<String, AttributeValue>> for Verification {
         };
 
         let expires = match map.remove("expires") {
-            Some(AttributeValue::S(s)) => {
+            Some(AttributeValue::N(s) | AttributeValue::S(s)) => {
                 let secs = s.parse::<i64>().map_err(|_| "invalid timestamp for field expires")?;
                 DateTime::from_timestamp(secs, 0).ok_or("invalid timestamp for field expires")?
             },
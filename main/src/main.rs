@@ -11,6 +11,7 @@ pub type Result<T> = std::result::Result<T, Box<dyn StdError>>;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("Hello, world!");
-    Ok(())
+    lambda_http::tracing::init_default_subscriber();
+    let config = config::Config::new().await?;
+    server::serve(config).await
 }
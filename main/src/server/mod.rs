@@ -0,0 +1,78 @@
+use lambda_http::http::header::{AUTHORIZATION, CONTENT_TYPE, HeaderValue};
+use lambda_http::{run, service_fn, Body, Request, Response};
+use lambda_http::http::{Method, StatusCode};
+use serde::{Serialize, de::DeserializeOwned};
+use super::domain::types::Error;
+use super::config::Config;
+
+
+mod routes;
+
+
+type Result<T> = std::result::Result<T, Error>;
+
+
+/// Runs the Lambda, dispatching every API Gateway event through `route`.
+pub async fn serve(config: Config) -> crate::Result<()> {
+    let config = &config;
+    let handler = service_fn(move |event: Request| async move {
+        let res = match route(event, config).await {
+            Ok(res) => res,
+            Err(err) => err.into()
+        };
+        Ok::<_, lambda_http::Error>(res)
+    });
+    run(handler).await.map_err(|err| -> Box<dyn std::error::Error> { err })
+}
+
+
+async fn route(event: Request, config: &Config) -> Result<Response<Body>> {
+    let path = event.uri().path().trim_end_matches('/').to_string();
+    match (event.method(), path.as_str()) {
+        (&Method::POST, "/signup") => routes::signup(event, config).await,
+        (&Method::POST, "/login") => routes::login(event, config).await,
+        (&Method::POST, "/verify") => routes::verify(event, config).await,
+        (&Method::GET, "/verify") => routes::verify_magic_link(event, config).await,
+        (&Method::POST, "/refresh") => routes::refresh(event, config).await,
+        (&Method::POST, "/logout") => routes::logout(event, config).await,
+        (&Method::GET, "/me") => routes::me(event, config).await,
+        (&Method::GET, "/keys") => routes::keys(event, config).await,
+        (&Method::GET, "/.well-known/jwks.json") => routes::jwks(event, config).await,
+        _ => Err(Error::Custom(StatusCode::NOT_FOUND, String::from("route not found"), format!("no route for {} {path}", event.method()).into()))
+    }
+}
+
+
+/// Deserializes the JSON body of a request, answering `400 Bad Request` when it is malformed.
+fn body<T: DeserializeOwned>(event: &Request) -> Result<T> {
+    serde_json::from_slice(event.body()).map_err(|err|Error::Custom(StatusCode::BAD_REQUEST, String::from("invalid request body"), err.into()))
+}
+
+
+/// Builds a JSON response with the given status.
+fn json<T: Serialize>(status: StatusCode, value: &T) -> Result<Response<Body>> {
+    let json = serde_json::to_string(value).map_err(|err|Error::InternalServerError(err.into()))?;
+    let mut res = Response::new(Body::Text(json));
+    res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    *res.status_mut() = status;
+    Ok(res)
+}
+
+
+/// Builds an empty response with the given status.
+fn empty(status: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::Empty);
+    *res.status_mut() = status;
+    res
+}
+
+
+/// Extracts the token from an `Authorization: Bearer <token>` header.
+fn bearer(event: &Request) -> Result<&str> {
+    let header = event.headers().get(AUTHORIZATION).ok_or(Error::InvalidToken)?;
+    let header = header.to_str().map_err(|_|Error::InvalidToken)?;
+    match header.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => Ok(token.trim()),
+        _ => Err(Error::InvalidToken)
+    }
+}
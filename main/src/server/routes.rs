@@ -0,0 +1,234 @@
+use super::super::domain::types::{Error, Either, Id, Uuid, User, EmailAddress, Verification, Token, RefreshToken, Revocation};
+use super::super::domain::services::authentication::AuthenticationService;
+use super::super::domain::services::verification::VerificationService;
+use super::super::domain::services::refresh::RefreshTokenService;
+use super::super::domain::services::revocation::RevocationService;
+use super::super::domain::services::manager::Manager;
+use super::super::domain::services::paseto::Paseto;
+use super::{Result, body, json, empty, bearer};
+use lambda_http::{Body, Request, RequestExt, Response};
+use lambda_http::http::header::{CACHE_CONTROL, HeaderValue};
+use lambda_http::http::StatusCode;
+use shared::{Keyring, Jwk, Verifier};
+use serde::{Serialize, Deserialize};
+use super::super::config::Config;
+use chrono::{Utc, DateTime, TimeDelta};
+use lettre::Address;
+
+
+#[derive(Debug, Deserialize)]
+struct SignupRequest {
+    email: Address,
+    password: String,
+    user_name: String,
+    first_name: String,
+    last_name: String
+}
+
+
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    email: Address,
+    password: String
+}
+
+
+#[derive(Debug, Deserialize)]
+struct VerifyRequest {
+    user_id: String,
+    code: u32
+}
+
+
+#[derive(Debug, Deserialize)]
+struct RefreshRequest {
+    refresh_token: String
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+struct LogoutRequest {
+    refresh_token: Option<String>
+}
+
+
+#[derive(Debug, Serialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: i64,
+    refresh_token: String
+}
+
+
+#[derive(Debug, Serialize)]
+struct KeySet<T> {
+    keys: Vec<T>
+}
+
+
+/// A verification key in PASERK form.
+#[derive(Debug, Serialize)]
+struct Paserk {
+    kid: String,
+    key: String,
+    expires: DateTime<Utc>
+}
+
+
+/// The public view of a `User`, without the password hash.
+#[derive(Debug, Serialize)]
+struct Profile {
+    id: Id,
+    email: String,
+    email_verified: bool,
+    user_name: String,
+    first_name: String,
+    last_name: String,
+    profile_picture: Option<String>,
+    created_at: DateTime<Utc>
+}
+
+
+impl From<User> for Profile {
+    fn from(user: User) -> Self {
+        let (email, email_verified) = match user.email {
+            EmailAddress::New(address) => (address.to_string(), false),
+            EmailAddress::Verified(address) => (address.to_string(), true)
+        };
+        Profile {
+            id: user.id,
+            email,
+            email_verified,
+            user_name: user.user_name,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            profile_picture: user.profile_picture,
+            created_at: user.created_at
+        }
+    }
+}
+
+
+pub async fn signup(event: Request, config: &Config) -> Result<Response<Body>> {
+    let request: SignupRequest = body(&event)?;
+    let user = User {
+        id: Id::new(),
+        email: EmailAddress::New(request.email),
+        user_name: request.user_name,
+        first_name: request.first_name,
+        last_name: request.last_name,
+        password: request.password,
+        profile_picture: None,
+        created_at: Utc::now(),
+        expires: None
+    };
+    let user = User::signup(&config.client, &config.hasher, &config.mail, &config.verify_url, user).await?;
+    json(StatusCode::CREATED, &Profile::from(user))
+}
+
+
+pub async fn login(event: Request, config: &Config) -> Result<Response<Body>> {
+    let request: LoginRequest = body(&event)?;
+    let user = User::login(&config.client, &config.hasher, request.email, request.password, config.require_verified_email).await?;
+    let refresh_token = RefreshToken::generate_refresh_token(&config.client, user.id.clone()).await?;
+    issue(user.id, refresh_token, config).await
+}
+
+
+pub async fn verify(event: Request, config: &Config) -> Result<Response<Body>> {
+    let request: VerifyRequest = body(&event)?;
+    let user_id = request.user_id.parse().map_err(|err|Error::Custom(StatusCode::BAD_REQUEST, String::from("invalid user_id"), err))?;
+    let user = Verification::verify_email(&config.client, Either::Left((user_id, request.code))).await?;
+    json(StatusCode::OK, &Profile::from(user))
+}
+
+
+pub async fn verify_magic_link(event: Request, config: &Config) -> Result<Response<Body>> {
+    let params = event.query_string_parameters();
+    let magic_id = params.first("magic_id").ok_or(Error::VerificationCodeNotFound)?;
+    let magic_id: Uuid = magic_id.parse().map_err(|err|Error::Custom(StatusCode::BAD_REQUEST, String::from("invalid magic_id"), err))?;
+    let user = Verification::verify_email(&config.client, Either::Right(magic_id)).await?;
+    json(StatusCode::OK, &Profile::from(user))
+}
+
+
+/// Rotates a refresh token and issues a new access token with it.
+pub async fn refresh(event: Request, config: &Config) -> Result<Response<Body>> {
+    let request: RefreshRequest = body(&event)?;
+    let refresh_token = RefreshToken::rotate_refresh_token(&config.client, &request.refresh_token).await?;
+    issue(refresh_token.user_id.clone(), refresh_token, config).await
+}
+
+
+/// Revokes the access token, and ends the session of the refresh token in the body if one is given.
+pub async fn logout(event: Request, config: &Config) -> Result<Response<Body>> {
+    let signature = bearer(&event)?;
+    let keyring = config.keyring(Some(signature)).await?;
+    let token = Revocation::verify_token(&config.client, signature, &keyring, &config.validation).await?;
+    let request: LogoutRequest = match event.body().is_empty() {
+        true => LogoutRequest::default(),
+        false => body(&event)?
+    };
+    if let Some(refresh_token) = request.refresh_token {
+        RefreshToken::revoke_refresh_token(&config.client, &token.subject, &refresh_token).await?;
+    }
+    Revocation::revoke_token(&config.client, &token).await?;
+    Ok(empty(StatusCode::NO_CONTENT))
+}
+
+
+pub async fn me(event: Request, config: &Config) -> Result<Response<Body>> {
+    let signature = bearer(&event)?;
+    let keyring = config.keyring(Some(signature)).await?;
+    let token = Revocation::verify_token(&config.client, signature, &keyring, &config.validation).await?;
+    match User::read(&config.client, token.subject).await? {
+        Some(user) => json(StatusCode::OK, &Profile::from(user)),
+        None => Err(Error::UserNotFound)
+    }
+}
+
+
+/// Publishes the verification keys in PASERK `k4.public` form.
+pub async fn keys(_event: Request, config: &Config) -> Result<Response<Body>> {
+    let keyring = config.keyring(None).await?;
+    let paserks = keyring.keys.iter().map(|key|Paserk{kid: key.id(), key: key.paserk(), expires: key.expires}).collect();
+    let res = json(StatusCode::OK, &KeySet{keys: paserks})?;
+    cached(res, &keyring)
+}
+
+
+/// Publishes the verification keys as a JWKS document.
+pub async fn jwks(_event: Request, config: &Config) -> Result<Response<Body>> {
+    let keyring = config.keyring(None).await?;
+    let jwks: Vec<Jwk> = keyring.keys.iter().map(|key|key.jwk()).collect();
+    let res = json(StatusCode::OK, &KeySet{keys: jwks})?;
+    cached(res, &keyring)
+}
+
+
+/// Lets clients cache the published keys until `keyrotator` may replace the current key.
+/// Once rotation is due, the keys are only cached for five minutes so new keys are picked up quickly.
+/// They are never cached longer than a `Verifier` caches its keyring, so clients pick up new keys as quickly as verifiers do.
+fn cached(mut res: Response<Body>, keyring: &Keyring) -> Result<Response<Body>> {
+    let max_age = match keyring.rotates_at() {
+        Some(rotates_at) => (rotates_at - Utc::now()).num_seconds().max(300),
+        None => 300
+    };
+    let max_age = max_age.min(TimeDelta::minutes(Verifier::MAX_AGE_MINUTES).num_seconds());
+    let value = HeaderValue::from_str(&format!("public, max-age={max_age}")).map_err(|err|Error::InternalServerError(err.into()))?;
+    res.headers_mut().insert(CACHE_CONTROL, value);
+    Ok(res)
+}
+
+
+/// Signs a new access token for `subject` and returns it along with `refresh_token`.
+async fn issue(subject: Id, refresh_token: RefreshToken, config: &Config) -> Result<Response<Body>> {
+    let signing_key = config.signing_key().await?;
+    let expires_in = TimeDelta::minutes(config.token_minutes);
+    let token = Token::new(config.issuer.clone(), subject, config.audience.clone(), expires_in, config.claims.clone());
+    let access_token = token.try_sign(&signing_key)?;
+    let refresh_token = refresh_token.secret();
+    let res = TokenResponse{access_token, token_type: "Bearer", expires_in: expires_in.num_seconds(), refresh_token};
+    json(StatusCode::OK, &res)
+}
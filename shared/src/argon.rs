@@ -3,7 +3,96 @@
 #[cfg_attr(feature = "client", derive(serde::Serialize))]
 pub enum Request {
     Hash(String),
-    Verify(String, String)
+    Verify(String, String),
+    Calibrate(Calibration),
+    /// Hashes each password, answered with a `Reply::Batch` of `Reply::Hash`.
+    HashBatch(Vec<String>),
+    /// Verifies each password against its hash, answered with a `Reply::Batch` of `Reply::Verified`.
+    VerifyBatch(Vec<(String, String)>)
+}
+
+/// The most items a `Request::HashBatch` or `Request::VerifyBatch` may hold.
+/// Each item is a full hash, so a larger batch would run past the function's timeout or memory.
+pub const MAX_BATCH: usize = 64;
+
+/// Asks argon to benchmark parameter sets on the host it runs on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+pub struct Calibration {
+    /// The longest a hash may take, in milliseconds.
+    pub target_ms: u64,
+    /// The most memory a hash may use, in KiB.
+    pub memory_kib: u32,
+    /// The lanes to hash with, the parallelism of the host when missing.
+    #[cfg_attr(feature = "server", serde(default))]
+    pub parallelism: Option<u32>
+}
+
+/// A parameter set and how long hashing with it took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+pub struct Benchmark {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub elapsed_ms: u64
+}
+
+/// What a successful `Request::Calibrate` returns.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+pub struct Calibrated {
+    /// The set with the most memory, then the most passes, that fits the budget. `None` when even the smallest set is too slow.
+    pub best: Option<Benchmark>,
+    /// Every set that was tried, in the order it was tried.
+    pub benchmarks: Vec<Benchmark>
+}
+
+/// What a successful `Request::Verify` returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+pub struct Verified {
+    /// The hash was made with a different algorithm, version or params than argon is configured with, so it should be hashed again.
+    #[cfg_attr(feature = "client", serde(default))]
+    pub rehash: bool
+}
+
+/// What a successful `Request` returns, `Hash` for `Request::Hash`, `Verified` for `Request::Verify`, `Calibrated` for `Request::Calibrate`
+/// and `Batch` for the batch requests, with a result for each item in the order of the items.
+/// `Calibrated` comes before `Verified` so untagged replies are not mistaken for a `Verified` with its default.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(untagged))]
+pub enum Reply {
+    Hash(String),
+    Batch(Vec<Result<Reply, HashError>>),
+    Calibrated(Calibrated),
+    Verified(Verified)
+}
+
+/// Why a `Request` failed. Serialized as its `code`, with the `detail` when there is one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(tag = "code", content = "detail", rename_all = "snake_case"))]
+pub enum HashError {
+    /// The password does not match the hash.
+    WrongPassword,
+    /// The stored hash is corrupt or made with an algorithm argon does not verify.
+    InvalidHash(String),
+    /// The hash was made with a pepper argon is not configured with.
+    UnknownPepper,
+    /// The request can not be carried out as asked, such as a calibration with too little memory.
+    InvalidRequest(String),
+    /// The request needs a capability argon does not have.
+    Unsupported,
+    /// Hashing failed.
+    Internal(String)
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +104,34 @@ pub enum Result<T, E> {
 }
 
 
+impl HashError {
+    /// The stable code of the error, it does not change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            HashError::WrongPassword => "wrong_password",
+            HashError::InvalidHash(_) => "invalid_hash",
+            HashError::UnknownPepper => "unknown_pepper",
+            HashError::InvalidRequest(_) => "invalid_request",
+            HashError::Unsupported => "unsupported",
+            HashError::Internal(_) => "internal"
+        }
+    }
+}
+
+
+impl std::fmt::Display for HashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashError::WrongPassword | HashError::UnknownPepper | HashError::Unsupported => write!(f, "{}", self.code()),
+            HashError::InvalidHash(detail) | HashError::InvalidRequest(detail) | HashError::Internal(detail) => write!(f, "{}: {detail}", self.code())
+        }
+    }
+}
+
+
+impl std::error::Error for HashError {}
+
+
 impl<T, E> From<Result<T, E>> for std::result::Result<T, E> {
     fn from(result: Result<T, E>) -> Self {
         match result {
@@ -22,4 +139,39 @@ impl<T, E> From<Result<T, E>> for std::result::Result<T, E> {
             Result::Err(err) => Err(err)
         }
     }
-}
\ No newline at end of file
+}
+
+impl<T, E> From<std::result::Result<T, E>> for Result<T, E> {
+    fn from(result: std::result::Result<T, E>) -> Self {
+        match result {
+            Ok(value) => Result::Ok(value),
+            Err(err) => Result::Err(err)
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "server", feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_error_codes() {
+        let json = serde_json::to_string(&Result::<Reply, HashError>::Err(HashError::WrongPassword)).unwrap();
+        assert_eq!(json, r#"{"Err":{"code":"wrong_password"}}"#);
+        let json = serde_json::to_string(&HashError::InvalidHash("invalid Base64 encoding".into())).unwrap();
+        assert_eq!(json, r#"{"code":"invalid_hash","detail":"invalid Base64 encoding"}"#);
+        let err: HashError = serde_json::from_str(&json).unwrap();
+        assert_eq!(err.code(), "invalid_hash");
+    }
+
+    #[test]
+    fn test_batch_reply() {
+        let reply = Reply::Batch(vec![Result::Ok(Reply::Verified(Verified{rehash: true})), Result::Err(HashError::WrongPassword)]);
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(json, r#"[{"Ok":{"rehash":true}},{"Err":{"code":"wrong_password"}}]"#);
+        let Reply::Batch(results) = serde_json::from_str(&json).unwrap() else { panic!("not a batch") };
+        assert!(matches!(results[0], Result::Ok(Reply::Verified(Verified{rehash: true}))));
+        assert!(matches!(results[1], Result::Err(HashError::WrongPassword)));
+    }
+}
@@ -0,0 +1,148 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+use serde::{Serialize, Deserialize};
+use std::fmt::{Debug, Formatter};
+use std::error::Error as StdError;
+use chrono::{Utc, DateTime};
+use super::SigningKey;
+use std::env::var;
+use base64::Engine;
+
+
+type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
+
+
+const NONCE_LENGTH: usize = 24;
+
+
+/// The key-encryption key the signing key is wrapped with before it is stored.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Kek {
+    key: [u8; 32]
+}
+
+
+/// The signing key as it is stored: its private key encrypted with a random data key, which is encrypted with the `Kek`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct WrappedSigningKey {
+    version: u32,
+    public_key: [u8; 32],
+    created_time: DateTime<Utc>,
+    expires: DateTime<Utc>,
+    /// The data key sealed with the key-encryption key.
+    data_key: String,
+    /// The private key sealed with the data key.
+    private_key: String
+}
+
+
+impl Kek {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self{key}
+    }
+
+    /// Reads the base64url encoded 32 byte key in `KEY_ENCRYPTION_KEY`.
+    pub fn from_env() -> Result<Self> {
+        let key = Zeroizing::new(URL_SAFE_NO_PAD.decode(var("KEY_ENCRYPTION_KEY")?)?);
+        let key = key.as_slice().try_into().map_err(|_|"KEY_ENCRYPTION_KEY has to be 32 bytes")?;
+        Ok(Self{key})
+    }
+
+    pub(crate) fn wrap(&self, signing_key: &SigningKey) -> Result<WrappedSigningKey> {
+        let data_key: Zeroizing<[u8; 32]> = Zeroizing::new(XChaCha20Poly1305::generate_key(&mut OsRng).into());
+        let aad = &signing_key.public_key;
+        Ok(WrappedSigningKey {
+            version: signing_key.version,
+            public_key: signing_key.public_key,
+            created_time: signing_key.created_time,
+            expires: signing_key.expires,
+            data_key: seal(&self.key, data_key.as_slice(), aad)?,
+            private_key: seal(&data_key, &signing_key.private_key, aad)?
+        })
+    }
+
+    pub(crate) fn unwrap(&self, wrapped: WrappedSigningKey) -> Result<SigningKey> {
+        let aad = &wrapped.public_key;
+        let data_key = open(&self.key, &wrapped.data_key, aad).map_err(|_|"the signing key could not be unwrapped with this key-encryption key")?;
+        let data_key: &[u8; 32] = data_key.as_slice().try_into().map_err(|_|"the data key is corrupt")?;
+        let private_key = open(data_key, &wrapped.private_key, aad)?;
+        Ok(SigningKey {
+            version: wrapped.version,
+            private_key: private_key.as_slice().try_into().map_err(|_|"the private key is corrupt")?,
+            public_key: wrapped.public_key,
+            created_time: wrapped.created_time,
+            expires: wrapped.expires
+        })
+    }
+}
+
+
+impl Debug for Kek {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Kek(..)")
+    }
+}
+
+
+/// Encrypts `plaintext` with XChaCha20-Poly1305 under a random nonce, returning the base64url encoded nonce and ciphertext.
+pub(crate) fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, Payload{msg: plaintext, aad}).map_err(|_|"encryption failed")?;
+    let mut bytes = nonce.to_vec();
+    bytes.extend(ciphertext);
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+
+/// Reverses `seal`.
+pub(crate) fn open(key: &[u8; 32], sealed: &str, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let bytes = URL_SAFE_NO_PAD.decode(sealed.trim())?;
+    if bytes.len() < NONCE_LENGTH {
+        return Err("the ciphertext is too short".into());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let plaintext = cipher.decrypt(XNonce::from_slice(nonce), Payload{msg: ciphertext, aad}).map_err(|_|"decryption failed")?;
+    Ok(Zeroizing::new(plaintext))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn signing_key() -> SigningKey {
+        let created_time = Utc::now();
+        SigningKey{version: 1, private_key: [1; 32], public_key: [2; 32], created_time, expires: created_time + TimeDelta::days(30)}
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let kek = Kek::new([9; 32]);
+        let wrapped = kek.wrap(&signing_key()).unwrap();
+        let json = serde_json::to_string(&wrapped).unwrap();
+        assert!(!json.contains(&serde_json::to_string(&[1u8; 32]).unwrap()));
+        let stored: WrappedSigningKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(kek.unwrap(stored).unwrap().private_key, [1; 32]);
+        let stored: WrappedSigningKey = serde_json::from_str(&json).unwrap();
+        assert!(Kek::new([8; 32]).unwrap(stored).is_err());
+    }
+
+    #[test]
+    fn test_reject_plain_signing_key() {
+        let json = r#"{"version":1,"private_key":[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],"public_key":[2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2],"created_time":"2030-01-01T00:00:00Z","expires":"2030-01-31T00:00:00Z"}"#;
+        assert!(serde_json::from_str::<WrappedSigningKey>(json).is_err());
+    }
+
+    #[test]
+    fn test_wrapped_key_is_bound_to_its_public_key() {
+        let kek = Kek::new([9; 32]);
+        let mut wrapped = kek.wrap(&signing_key()).unwrap();
+        wrapped.public_key = [3; 32];
+        assert!(kek.unwrap(wrapped).is_err());
+    }
+}
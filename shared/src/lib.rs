@@ -1,6 +1,32 @@
 mod paseto;
+mod store;
+mod envelope;
 mod argon;
+mod protocol;
+#[cfg(feature = "verifier")]
+mod token;
+#[cfg(feature = "verifier")]
+mod validation;
+#[cfg(feature = "verifier")]
+mod verifier;
+#[cfg(feature = "layer")]
+mod layer;
+#[cfg(feature = "table")]
+mod table;
 
 
 pub use paseto::*;
-pub use argon::*;
\ No newline at end of file
+pub use store::*;
+pub use envelope::*;
+pub use argon::*;
+pub use protocol::*;
+#[cfg(feature = "verifier")]
+pub use token::*;
+#[cfg(feature = "verifier")]
+pub use validation::*;
+#[cfg(feature = "verifier")]
+pub use verifier::*;
+#[cfg(feature = "layer")]
+pub use layer::*;
+#[cfg(feature = "table")]
+pub use table::*;
\ No newline at end of file
@@ -1,51 +1,323 @@
-use aws_sdk_ssm::types::ParameterType;
+use blake2::digest::{Update, VariableOutput};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use serde::{Serialize, Deserialize};
 use std::error::Error as StdError;
-use chrono::{Utc, DateTime};
-use aws_sdk_ssm::Client;
+use chrono::{Utc, DateTime, TimeDelta};
+use super::{KeyStore, Parameters, Kek, WrappedSigningKey};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use std::fmt::{Debug, Formatter};
+use blake2::Blake2bVar;
+use base64::Engine;
 
 
 pub use aws_sdk_ssm;
 
 
-pub const PARAMETER_NAME: &'static str = "interphlix/authentication/paseto_keys";
+/// Keys are rotated once they are this close to expiring.
+pub const ROTATION_DAYS: i64 = 1;
 
 
 type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
 
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct Keys {
+/// An Ed25519 public key that tokens can be verified with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PublicKey {
+    pub version: u32,
+    pub key: [u8; 32],
+    pub created_time: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    /// When a newer key replaced this one, no tokens are signed with it after that.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired: Option<DateTime<Utc>>
+}
+
+
+/// A public key in JWK form (RFC 8037).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub kid: String,
+    #[serde(rename = "use")]
+    pub r#use: String,
+    pub alg: String
+}
+
+
+/// The footer of tokens signed by this service, it names the key that signed the token.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyFooter {
+    pub kid: String
+}
+
+
+/// The Ed25519 key pair tokens are currently signed with.
+/// It is never serialized, `Keys::put` wraps it under a `Kek` first, and its key material is wiped when it is dropped.
+#[derive(Clone, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct SigningKey {
+    pub version: u32,
     pub private_key: [u8; 32],
     pub public_key: [u8; 32],
-    pub prev_public_key: Option<[u8; 32]>,
+    #[zeroize(skip)]
     pub created_time: DateTime<Utc>,
+    #[zeroize(skip)]
     pub expires: DateTime<Utc>
 }
 
 
+/// Every public key tokens may still be signed with, the current key first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Keyring {
+    pub keys: Vec<PublicKey>,
+    /// Keys that were dropped before they expired because they were compromised.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revoked: Vec<RevokedKey>
+}
+
+
+/// A key that was removed from the keyring by `keyrotator`'s `Revoke` command.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RevokedKey {
+    pub kid: String,
+    pub version: u32,
+    pub reason: String,
+    pub revoked_time: DateTime<Utc>,
+    pub expires: DateTime<Utc>
+}
+
+
+/// The signing key together with the keyring it belongs to, as managed by `keyrotator`.
+#[derive(Debug, Clone)]
+pub struct Keys {
+    pub signing_key: SigningKey,
+    pub keyring: Keyring
+}
+
+
+impl PublicKey {
+    /// The key in PASERK `k4.public` form.
+    pub fn paserk(&self) -> String {
+        format!("k4.public.{}", URL_SAFE_NO_PAD.encode(self.key))
+    }
+
+    /// The PASERK `k4.pid` identifier of the key.
+    pub fn id(&self) -> String {
+        let header = "k4.pid.";
+        let mut hasher = Blake2bVar::new(33).expect("33 is a valid blake2b output size");
+        hasher.update(header.as_bytes());
+        hasher.update(self.paserk().as_bytes());
+        let mut digest = [0u8; 33];
+        hasher.finalize_variable(&mut digest).expect("the buffer matches the output size");
+        format!("{header}{}", URL_SAFE_NO_PAD.encode(digest))
+    }
+
+    pub fn jwk(&self) -> Jwk {
+        Jwk {
+            kty: String::from("OKP"),
+            crv: String::from("Ed25519"),
+            x: URL_SAFE_NO_PAD.encode(self.key),
+            kid: self.id(),
+            r#use: String::from("sig"),
+            alg: String::from("EdDSA")
+        }
+    }
+}
+
+
+impl KeyFooter {
+    pub fn new(key: &PublicKey) -> Self {
+        Self{kid: key.id()}
+    }
+
+    /// Reads the footer of `token`.
+    /// Returns the footer as it was signed, which has to be passed on for verification, along with its parsed form.
+    pub fn decode(token: &str) -> Option<(String, KeyFooter)> {
+        let footer = token.split('.').nth(3)?;
+        let footer = String::from_utf8(URL_SAFE_NO_PAD.decode(footer).ok()?).ok()?;
+        let key_footer = serde_json::from_str(&footer).ok()?;
+        Some((footer, key_footer))
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::json!({"kid": self.kid}).to_string()
+    }
+}
+
+
+impl Debug for SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("version", &self.version)
+            .field("public_key", &self.public_key)
+            .field("created_time", &self.created_time)
+            .field("expires", &self.expires)
+            .finish_non_exhaustive()
+    }
+}
+
+
+impl SigningKey {
+    /// The public half of the key.
+    pub fn public(&self) -> PublicKey {
+        PublicKey{version: self.version, key: self.public_key, created_time: self.created_time, expires: self.expires, retired: None}
+    }
+
+    /// When `keyrotator` will replace the key.
+    pub fn rotates_at(&self) -> DateTime<Utc> {
+        self.expires - TimeDelta::days(ROTATION_DAYS)
+    }
+}
+
+
+impl Keyring {
+    /// The key tokens are currently signed with.
+    pub fn current(&self) -> Option<&PublicKey> {
+        self.keys.first()
+    }
+
+    /// Looks up a verification key by its PASERK `k4.pid` identifier.
+    pub fn find(&self, id: &str) -> Option<&PublicKey> {
+        self.keys.iter().find(|key|key.id() == id)
+    }
+
+    /// When `keyrotator` will replace the current key.
+    pub fn rotates_at(&self) -> Option<DateTime<Utc>> {
+        self.current().map(|key|key.expires - TimeDelta::days(ROTATION_DAYS))
+    }
+
+    /// Makes `key` the current key and retires the others.
+    /// Retired keys are kept for `retention`, which should cover the longest lifetime of a token, so tokens they signed stay verifiable.
+    pub fn rotate(&mut self, key: PublicKey, retention: TimeDelta) {
+        let now = Utc::now();
+        for key in self.keys.iter_mut() {
+            key.retired.get_or_insert(now);
+        }
+        self.prune(now - retention);
+        self.keys.insert(0, key);
+    }
+
+    /// Drops the keys that were retired before `cutoff`, along with the records of revoked keys that have expired since.
+    pub fn prune(&mut self, cutoff: DateTime<Utc>) {
+        self.keys.retain(|key|key.retired.is_none_or(|retired|retired >= cutoff));
+        self.revoked.retain(|key|key.expires >= cutoff);
+    }
+
+    /// Removes the key named `id` so tokens it signed are no longer accepted, and records why.
+    pub fn revoke(&mut self, id: &str, reason: String) -> Option<RevokedKey> {
+        let index = self.keys.iter().position(|key|key.id() == id)?;
+        let key = self.keys.remove(index);
+        let revoked = RevokedKey{kid: key.id(), version: key.version, reason, revoked_time: Utc::now(), expires: key.expires};
+        self.revoked.push(revoked.clone());
+        Some(revoked)
+    }
+}
+
 
 impl Keys {
-    pub async fn get(client: &Client) -> Result<Option<Keys>> {
-        let name = PARAMETER_NAME;
-        let output = client.get_parameter().name(name).with_decryption(true).send().await?;
-        let parameter = match output.parameter {
-            Some(parameter) => parameter,
-            None => return Ok(None)
-        };
-        let json = match parameter.value {
-            Some(json) => json,
+    pub async fn get(store: &impl KeyStore, parameters: &Parameters, kek: &Kek) -> Result<Option<Keys>> {
+        let signing_key = match Self::get_signing(store, parameters, kek).await? {
+            Some(signing_key) => signing_key,
             None => return Ok(None)
         };
-        let keys = serde_json::from_str(&json)?;
-        Ok(Some(keys))
-    }
-    
-    pub async fn put(&self, client: &Client) -> Result<()> {
-        let json = serde_json::to_string(self)?;
-        let name = PARAMETER_NAME;
-        let r#type = ParameterType::SecureString;
-        let _ = client.put_parameter().name(name).r#type(r#type).overwrite(true).value(json).send().await?;
-        Ok(())
-    }
-}
\ No newline at end of file
+        let keyring = Self::get_verifying(store, parameters).await?.unwrap_or_default();
+        Ok(Some(Keys{signing_key, keyring}))
+    }
+
+    /// Reads and unwraps the signing key, this requires access to the signing parameter and the `Kek`.
+    pub async fn get_signing(store: &impl KeyStore, parameters: &Parameters, kek: &Kek) -> Result<Option<SigningKey>> {
+        match get::<WrappedSigningKey>(store, &parameters.signing_name()).await? {
+            Some(wrapped) => Ok(Some(kek.unwrap(wrapped)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Reads the public keyring, this only requires access to the verifying parameter.
+    pub async fn get_verifying(store: &impl KeyStore, parameters: &Parameters) -> Result<Option<Keyring>> {
+        get(store, &parameters.verifying_name()).await
+    }
+
+    /// Writes the keyring before the signing key, so tokens are never signed with a key verifiers cannot know about.
+    pub async fn put(&self, store: &impl KeyStore, parameters: &Parameters, kek: &Kek) -> Result<()> {
+        put(store, &parameters.verifying_name(), &self.keyring, false).await?;
+        put(store, &parameters.signing_name(), &kek.wrap(&self.signing_key)?, true).await
+    }
+}
+
+
+async fn get<T: serde::de::DeserializeOwned>(store: &impl KeyStore, name: &str) -> Result<Option<T>> {
+    let json = match store.get(name).await? {
+        Some(json) => json,
+        None => return Ok(None)
+    };
+    let value = serde_json::from_str(&json)?;
+    Ok(Some(value))
+}
+
+
+async fn put<T: Serialize>(store: &impl KeyStore, name: &str, value: &T, secret: bool) -> Result<()> {
+    let json = serde_json::to_string(value)?;
+    store.put(name, json, secret).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::MemoryStore;
+
+    fn key(version: u32) -> PublicKey {
+        let created_time = Utc::now();
+        PublicKey{version, key: [version as u8; 32], created_time, expires: created_time + TimeDelta::days(30), retired: None}
+    }
+
+    #[test]
+    fn test_rotate_keeps_retired_keys_within_retention() {
+        let mut keyring = Keyring{keys: vec![key(1)], ..Default::default()};
+        keyring.rotate(key(2), TimeDelta::hours(1));
+        keyring.rotate(key(3), TimeDelta::hours(1));
+        let versions: Vec<u32> = keyring.keys.iter().map(|key|key.version).collect();
+        assert_eq!(versions, vec![3, 2, 1]);
+        assert!(keyring.current().unwrap().retired.is_none());
+        assert!(keyring.keys[1..].iter().all(|key|key.retired.is_some()));
+    }
+
+    #[test]
+    fn test_rotate_prunes_expired_history() {
+        let mut old = key(1);
+        old.retired = Some(Utc::now() - TimeDelta::hours(2));
+        let mut keyring = Keyring{keys: vec![key(2), old], ..Default::default()};
+        keyring.rotate(key(3), TimeDelta::hours(1));
+        let versions: Vec<u32> = keyring.keys.iter().map(|key|key.version).collect();
+        assert_eq!(versions, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_revoke() {
+        let mut keyring = Keyring{keys: vec![key(1)], ..Default::default()};
+        keyring.rotate(key(2), TimeDelta::hours(1));
+        let kid = key(1).id();
+        let revoked = keyring.revoke(&kid, "leaked".into()).unwrap();
+        assert_eq!(revoked.version, 1);
+        assert!(keyring.find(&kid).is_none());
+        assert_eq!(keyring.revoked, vec![revoked]);
+        assert!(keyring.revoke(&kid, "leaked".into()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_keys_round_trip() {
+        let store = MemoryStore::default();
+        let parameters = Parameters::default();
+        let kek = Kek::new([9; 32]);
+        assert!(Keys::get(&store, &parameters, &kek).await.unwrap().is_none());
+        let created_time = Utc::now();
+        let signing_key = SigningKey{version: 1, private_key: [1; 32], public_key: [2; 32], created_time, expires: created_time + TimeDelta::days(30)};
+        let keys = Keys{keyring: Keyring{keys: vec![signing_key.public()], ..Default::default()}, signing_key};
+        keys.put(&store, &parameters, &kek).await.unwrap();
+        let stored = Keys::get(&store, &parameters, &kek).await.unwrap().unwrap();
+        assert_eq!(stored.keyring, keys.keyring);
+        assert_eq!(stored.signing_key.private_key, keys.signing_key.private_key);
+        assert!(Keys::get(&store, &Parameters{namespace: "interphlix/staging".into(), ..parameters}, &kek).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use super::argon::{Request, Reply, Verified, HashError, Result};
+
+
+/// The version of the argon protocol this build speaks.
+/// Version 0 is the bare `Request` answered with a `Result<Option<String>, String>`, from before requests were wrapped in a `VersionedRequest`.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// What this build can do, sent along with every versioned request and response.
+pub const CAPABILITIES: &[Capability] = &[Capability::Hash, Capability::Verify, Capability::Rehash, Capability::Calibrate, Capability::Batch];
+
+/// How version 0 tells a wrong password apart from the other errors.
+const VERSION_0_WRONG_PASSWORD: &str = "invalid password";
+
+
+/// Something a side of the protocol can do, so the other side only sends it what it understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(any(feature = "server", feature = "client"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(rename_all = "snake_case"))]
+pub enum Capability {
+    Hash,
+    Verify,
+    /// `Verified::rehash` is reported.
+    Rehash,
+    Calibrate,
+    /// `Request::HashBatch` and `Request::VerifyBatch`.
+    Batch,
+    /// A capability of a newer build.
+    #[cfg_attr(any(feature = "server", feature = "client"), serde(other))]
+    Unknown
+}
+
+
+/// A request this build may not know, because the other side is newer.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(untagged))]
+pub enum Supported<T> {
+    Known(T),
+    Unknown(serde_json::Value)
+}
+
+
+/// A request since version 1.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+pub struct VersionedRequest {
+    pub version: u32,
+    pub capabilities: Vec<Capability>,
+    pub request: Supported<Request>
+}
+
+
+/// A response since version 1.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+pub struct VersionedResponse {
+    pub version: u32,
+    pub capabilities: Vec<Capability>,
+    pub result: Result<Reply, HashError>
+}
+
+
+/// A request of any protocol version.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(untagged))]
+pub enum AnyRequest {
+    Versioned(VersionedRequest),
+    Unversioned(Request)
+}
+
+
+/// A response of any protocol version, answering an `AnyRequest` of the same version.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "server", derive(serde::Serialize))]
+#[cfg_attr(feature = "client", derive(serde::Deserialize))]
+#[cfg_attr(any(feature = "server", feature = "client"), serde(untagged))]
+pub enum AnyResponse {
+    Versioned(VersionedResponse),
+    Unversioned(Result<Option<String>, String>)
+}
+
+
+impl VersionedRequest {
+    pub fn new(request: Request) -> Self {
+        VersionedRequest{version: PROTOCOL_VERSION, capabilities: CAPABILITIES.to_vec(), request: Supported::Known(request)}
+    }
+}
+
+
+impl AnyRequest {
+    /// The request if this build knows it.
+    pub fn request(&self) -> Option<&Request> {
+        match self {
+            AnyRequest::Versioned(VersionedRequest{request: Supported::Known(request), ..}) | AnyRequest::Unversioned(request) => Some(request),
+            _ => None
+        }
+    }
+
+    /// Answers the request in its own protocol version.
+    pub fn respond(&self, result: std::result::Result<Reply, HashError>) -> AnyResponse {
+        match self {
+            AnyRequest::Versioned(_) => AnyResponse::Versioned(VersionedResponse {
+                version: PROTOCOL_VERSION,
+                capabilities: CAPABILITIES.to_vec(),
+                result: result.into()
+            }),
+            AnyRequest::Unversioned(_) => AnyResponse::Unversioned(match result {
+                Ok(Reply::Hash(hash)) => Result::Ok(Some(hash)),
+                Ok(Reply::Verified(_)) => Result::Ok(None),
+                Ok(Reply::Calibrated(_) | Reply::Batch(_)) => Result::Err(HashError::Unsupported.to_string()),
+                Err(HashError::WrongPassword) => Result::Err(VERSION_0_WRONG_PASSWORD.to_string()),
+                Err(err) => Result::Err(err.to_string())
+            })
+        }
+    }
+}
+
+
+impl AnyResponse {
+    /// The capabilities of whoever responded. Version 0 could only hash and verify.
+    pub fn capabilities(&self) -> Vec<Capability> {
+        match self {
+            AnyResponse::Versioned(response) => response.capabilities.clone(),
+            AnyResponse::Unversioned(_) => vec![Capability::Hash, Capability::Verify]
+        }
+    }
+
+    /// The result the response carries, reading a version 0 `Ok(None)` as a `Verified` without `rehash`.
+    /// Version 0 only told a wrong password apart by its message.
+    pub fn result(self) -> std::result::Result<Reply, HashError> {
+        match self {
+            AnyResponse::Versioned(response) => response.result.into(),
+            AnyResponse::Unversioned(Result::Ok(Some(hash))) => Ok(Reply::Hash(hash)),
+            AnyResponse::Unversioned(Result::Ok(None)) => Ok(Reply::Verified(Verified::default())),
+            AnyResponse::Unversioned(Result::Err(err)) if err == VERSION_0_WRONG_PASSWORD => Err(HashError::WrongPassword),
+            AnyResponse::Unversioned(Result::Err(err)) => Err(HashError::Internal(err))
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "server", feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_version_0_requests() {
+        let request: AnyRequest = serde_json::from_str(r#"{"Verify":["password","$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"]}"#).unwrap();
+        assert!(matches!(request, AnyRequest::Unversioned(Request::Verify(..))));
+        let response = serde_json::to_string(&request.respond(Ok(Reply::Verified(Verified{rehash: true})))).unwrap();
+        assert_eq!(response, r#"{"Ok":null}"#);
+        let request: AnyRequest = serde_json::from_str(r#"{"Hash":"password"}"#).unwrap();
+        let response = serde_json::to_string(&request.respond(Err(HashError::Internal("out of memory".into())))).unwrap();
+        assert_eq!(response, r#"{"Err":"internal: out of memory"}"#);
+    }
+
+    #[test]
+    fn test_decode_version_0_responses() {
+        let response: AnyResponse = serde_json::from_str(r#"{"Ok":"$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"}"#).unwrap();
+        assert_eq!(response.capabilities(), vec![Capability::Hash, Capability::Verify]);
+        assert!(matches!(response.result(), Ok(Reply::Hash(_))));
+        let response: AnyResponse = serde_json::from_str(r#"{"Ok":null}"#).unwrap();
+        assert!(matches!(response.result(), Ok(Reply::Verified(Verified{rehash: false}))));
+        let response: AnyResponse = serde_json::from_str(r#"{"Err":"invalid password"}"#).unwrap();
+        assert_eq!(response.result().err(), Some(HashError::WrongPassword));
+    }
+
+    #[test]
+    fn test_wrong_password_over_version_0() {
+        let request: AnyRequest = serde_json::from_str(r#"{"Verify":["wrong","$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"]}"#).unwrap();
+        let response = serde_json::to_string(&request.respond(Err(HashError::WrongPassword))).unwrap();
+        assert_eq!(response, r#"{"Err":"invalid password"}"#);
+        let response: AnyResponse = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.result().err(), Some(HashError::WrongPassword));
+    }
+
+    #[test]
+    fn test_newer_requests_and_capabilities() {
+        let json = r#"{"version":2,"capabilities":["hash","verify","forget"],"request":{"Forget":"password"}}"#;
+        let request: AnyRequest = serde_json::from_str(json).unwrap();
+        assert!(request.request().is_none());
+        let response = serde_json::to_string(&request.respond(Err(HashError::Unsupported))).unwrap();
+        let response: AnyResponse = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.capabilities(), CAPABILITIES);
+        assert_eq!(response.result().err(), Some(HashError::Unsupported));
+        let request = serde_json::to_value(AnyRequest::Versioned(VersionedRequest::new(Request::Hash("password".into())))).unwrap();
+        assert_eq!(request["version"], PROTOCOL_VERSION);
+        assert_eq!(request["request"], serde_json::json!({"Hash": "password"}));
+    }
+}
@@ -0,0 +1,293 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use super::envelope::{seal, open};
+use aws_sdk_ssm::operation::delete_parameter::DeleteParameterError;
+use aws_sdk_ssm::operation::get_parameter::GetParameterError;
+use aws_sdk_ssm::types::ParameterType;
+use std::error::Error as StdError;
+use std::sync::{Mutex, PoisonError};
+use std::collections::HashMap;
+use aws_sdk_ssm::Client;
+use std::future::Future;
+use std::path::PathBuf;
+use std::env::var;
+use base64::Engine;
+
+
+type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
+
+
+/// The namespace the parameters live under unless `KEYS_NAMESPACE` says otherwise.
+pub const NAMESPACE: &str = "interphlix/authentication";
+/// Holds the `SigningKey`, only the authentication service should be able to read it.
+pub const SIGNING_PARAMETER: &str = "paseto_signing_key";
+/// Holds the public `Keyring`, any service verifying tokens may read it.
+pub const VERIFYING_PARAMETER: &str = "paseto_public_keys";
+/// Held the signing key and the keyring in plaintext before they were split and wrapped.
+pub const LEGACY_PARAMETER: &str = "paseto_keys";
+
+
+/// Somewhere `Keys` can be read from and written to.
+pub trait KeyStore {
+    fn get(&self, name: &str) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// `secret` values must not be readable by services that only verify tokens.
+    fn put(&self, name: &str, value: String, secret: bool) -> impl Future<Output = Result<()>> + Send;
+
+    /// Deleting a value that does not exist is not an error.
+    fn delete(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+
+/// The names of the parameters holding the keys, so each environment can keep its own keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameters {
+    pub namespace: String,
+    pub signing: String,
+    pub verifying: String
+}
+
+
+/// Keeps the keys in a single file, encrypted with XChaCha20-Poly1305.
+/// Meant for running and testing the services offline.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+    key: [u8; 32]
+}
+
+
+/// Keeps the keys in memory, they are lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    values: Mutex<HashMap<String, String>>
+}
+
+
+/// The key store picked through `KEY_STORE`.
+#[derive(Debug)]
+pub enum Store {
+    Ssm(Client),
+    File(FileStore),
+    Memory(MemoryStore)
+}
+
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Parameters {
+            namespace: String::from(NAMESPACE),
+            signing: String::from(SIGNING_PARAMETER),
+            verifying: String::from(VERIFYING_PARAMETER)
+        }
+    }
+}
+
+
+impl Parameters {
+    /// Reads `KEYS_NAMESPACE`, `SIGNING_PARAMETER` and `VERIFYING_PARAMETER`, falling back to the defaults.
+    pub fn from_env() -> Self {
+        let default = Parameters::default();
+        Parameters {
+            namespace: var("KEYS_NAMESPACE").unwrap_or(default.namespace),
+            signing: var("SIGNING_PARAMETER").unwrap_or(default.signing),
+            verifying: var("VERIFYING_PARAMETER").unwrap_or(default.verifying)
+        }
+    }
+
+    pub fn signing_name(&self) -> String {
+        format!("{}/{}", self.namespace.trim_end_matches('/'), self.signing)
+    }
+
+    pub fn verifying_name(&self) -> String {
+        format!("{}/{}", self.namespace.trim_end_matches('/'), self.verifying)
+    }
+
+    pub fn legacy_name(&self) -> String {
+        format!("{}/{}", self.namespace.trim_end_matches('/'), LEGACY_PARAMETER)
+    }
+}
+
+
+impl KeyStore for Client {
+    async fn get(&self, name: &str) -> Result<Option<String>> {
+        let output = match self.get_parameter().name(name).with_decryption(true).send().await {
+            Ok(output) => output,
+            Err(err) => match err.into_service_error() {
+                GetParameterError::ParameterNotFound(_) => return Ok(None),
+                err => return Err(err.into())
+            }
+        };
+        Ok(output.parameter.and_then(|parameter|parameter.value))
+    }
+
+    async fn put(&self, name: &str, value: String, secret: bool) -> Result<()> {
+        let r#type = match secret {
+            true => ParameterType::SecureString,
+            false => ParameterType::String
+        };
+        let _ = self.put_parameter().name(name).r#type(r#type).overwrite(true).value(value).send().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        match self.delete_parameter().name(name).send().await {
+            Ok(_) => Ok(()),
+            Err(err) => match err.into_service_error() {
+                DeleteParameterError::ParameterNotFound(_) => Ok(()),
+                err => Err(err.into())
+            }
+        }
+    }
+}
+
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>, key: [u8; 32]) -> Self {
+        Self{path: path.into(), key}
+    }
+
+    fn read(&self) -> Result<HashMap<String, String>> {
+        let encoded = match std::fs::read_to_string(&self.path) {
+            Ok(encoded) => encoded,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err.into())
+        };
+        let json = open(&self.key, &encoded, &[]).map_err(|_|"the key store file could not be decrypted")?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn write(&self, values: &HashMap<String, String>) -> Result<()> {
+        let json = serde_json::to_vec(values)?;
+        let sealed = seal(&self.key, &json, &[])?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, sealed)?;
+        Ok(())
+    }
+}
+
+
+impl KeyStore for FileStore {
+    async fn get(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.read()?.remove(name))
+    }
+
+    async fn put(&self, name: &str, value: String, _secret: bool) -> Result<()> {
+        let mut values = self.read()?;
+        values.insert(name.to_string(), value);
+        self.write(&values)
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        let mut values = self.read()?;
+        if values.remove(name).is_some() {
+            self.write(&values)?;
+        }
+        Ok(())
+    }
+}
+
+
+impl KeyStore for MemoryStore {
+    async fn get(&self, name: &str) -> Result<Option<String>> {
+        let values = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(values.get(name).cloned())
+    }
+
+    async fn put(&self, name: &str, value: String, _secret: bool) -> Result<()> {
+        let mut values = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+        values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        let mut values = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+        values.remove(name);
+        Ok(())
+    }
+}
+
+
+impl Store {
+    /// Picks the store named in `KEY_STORE`: `ssm` (the default), `file` or `memory`.
+    /// The file store reads its path from `KEY_STORE_PATH` and its base64url encoded 32 byte key from `KEY_STORE_KEY`.
+    pub fn from_env(client: Client) -> Result<Self> {
+        match var("KEY_STORE").unwrap_or("ssm".into()).as_str() {
+            "ssm" => Ok(Store::Ssm(client)),
+            "memory" => Ok(Store::Memory(MemoryStore::default())),
+            "file" => {
+                let path = var("KEY_STORE_PATH").unwrap_or("keys.enc".into());
+                let key = URL_SAFE_NO_PAD.decode(var("KEY_STORE_KEY")?)?;
+                let key = key.try_into().map_err(|_|"KEY_STORE_KEY has to be 32 bytes")?;
+                Ok(Store::File(FileStore::new(path, key)))
+            },
+            store => Err(format!("unknown key store {store}").into())
+        }
+    }
+}
+
+
+impl From<Client> for Store {
+    fn from(client: Client) -> Self {
+        Store::Ssm(client)
+    }
+}
+
+
+impl KeyStore for Store {
+    async fn get(&self, name: &str) -> Result<Option<String>> {
+        match self {
+            Store::Ssm(client) => client.get(name).await,
+            Store::File(store) => store.get(name).await,
+            Store::Memory(store) => store.get(name).await
+        }
+    }
+
+    async fn put(&self, name: &str, value: String, secret: bool) -> Result<()> {
+        match self {
+            Store::Ssm(client) => client.put(name, value, secret).await,
+            Store::File(store) => store.put(name, value, secret).await,
+            Store::Memory(store) => store.put(name, value, secret).await
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        match self {
+            Store::Ssm(client) => client.delete(name).await,
+            Store::File(store) => store.delete(name).await,
+            Store::Memory(store) => store.delete(name).await
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_names() {
+        let parameters = Parameters{namespace: "interphlix/staging/".into(), ..Default::default()};
+        assert_eq!(parameters.signing_name(), "interphlix/staging/paseto_signing_key");
+        assert_eq!(Parameters::default().verifying_name(), "interphlix/authentication/paseto_public_keys");
+        assert_eq!(Parameters::default().legacy_name(), "interphlix/authentication/paseto_keys");
+    }
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let path = std::env::temp_dir().join(format!("keys-{}.enc", std::process::id()));
+        let store = FileStore::new(&path, [7; 32]);
+        assert_eq!(store.get("signing").await.unwrap(), None);
+        store.put("signing", "secret".into(), true).await.unwrap();
+        store.put("verifying", "public".into(), false).await.unwrap();
+        assert_eq!(store.get("signing").await.unwrap(), Some("secret".into()));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+        assert!(FileStore::new(&path, [8; 32]).get("signing").await.is_err());
+        store.delete("signing").await.unwrap();
+        store.delete("signing").await.unwrap();
+        assert_eq!(store.get("signing").await.unwrap(), None);
+        assert_eq!(store.get("verifying").await.unwrap(), Some("public".into()));
+        std::fs::remove_file(path).unwrap();
+    }
+}
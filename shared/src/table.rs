@@ -0,0 +1,299 @@
+use aws_sdk_dynamodb::operation::{get_item::GetItemError, query::QueryError, put_item::PutItemError, update_item::UpdateItemError, delete_item::DeleteItemError};
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use aws_sdk_dynamodb::config::http::HttpResponse;
+use aws_sdk_dynamodb::error::SdkError;
+use std::fmt::{Display, Formatter};
+use std::error::Error as StdError;
+use std::collections::HashMap;
+use aws_sdk_dynamodb::Client;
+
+
+/// An item is looked up by its primary key (`Right`) or by the key of the table's Global Secondary Index (`Left`).
+pub enum Either<R, L> {
+    Right(R),
+    Left(L)
+}
+
+
+/// The item to change does not exist.
+#[derive(Debug)]
+pub struct ItemNotFound;
+
+
+/// The `Table` trait provides a set of methods for interacting with a database table.
+/// It requires the implementing type to be convertible to and from a `HashMap` of `AttributeValue`.
+// Callers use it with concrete types, so they can still tell whether its futures are `Send`.
+#[allow(async_fn_in_trait)]
+pub trait Table : Into<HashMap<String, AttributeValue>> + TryFrom<HashMap<String, AttributeValue>> {
+    type PK : Into<AttributeValue>;
+    type SK: Into<AttributeValue>;
+    /// What every operation fails with, it is built from the errors of DynamoDB and of decoding items.
+    type Error: From<SdkError<GetItemError, HttpResponse>>
+        + From<SdkError<QueryError, HttpResponse>>
+        + From<SdkError<PutItemError, HttpResponse>>
+        + From<SdkError<UpdateItemError, HttpResponse>>
+        + From<SdkError<DeleteItemError, HttpResponse>>
+        + From<<Self as TryFrom<HashMap<String, AttributeValue>>>::Error>
+        + From<ItemNotFound>;
+    const NAME: &'static str;
+    const PK_NAME: &'static str;
+    /// This is the Global Secondary Index's PK.
+    const SK_NAME: &'static str;
+    /// The name of the Global Secondary Index keyed by `SK_NAME`.
+    const INDEX_NAME: &'static str;
+    /// The sort key of the table's primary key, if it has one.
+    const SORT_NAME: Option<&'static str> = None;
+
+    /// Checks if an item exists in the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `key` - The primary or secondary key of the item.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a boolean indicating whether the item exists.
+    async fn item_exists(client: &Client, key: Either<Self::PK, Self::SK>) -> Result<bool, <Self as Table>::Error> {
+        Ok(Self::get_item(client, key).await?.is_some())
+    }
+
+    /// Retrieves an item from the database using either the primary key or secondary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `key` - The primary or secondary key of the item.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `Option` with the item if found, or `None` if not found.
+    async fn get_item(client: &Client, key: Either<Self::PK, Self::SK>) -> Result<Option<Self>, <Self as Table>::Error> {
+        let item = match key {
+            Either::Right(pk) => match Self::SORT_NAME {
+                None => {
+                    let output = client.get_item()
+                        .table_name(Self::NAME)
+                        .key(Self::PK_NAME, pk.into())
+                        .send().await?;
+                    output.item
+                },
+                Some(_) => Self::query_item(client, None, Self::PK_NAME, pk.into()).await?
+            },
+            Either::Left(sk) => Self::query_item(client, Some(Self::INDEX_NAME), Self::SK_NAME, sk.into()).await?
+        };
+
+        match item {
+            Some(map) => Ok(Some(map.try_into()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves the first item whose `name` attribute is `value`, from the table itself or from the index `index_name`.
+    async fn query_item(client: &Client, index_name: Option<&str>, name: &str, value: AttributeValue) -> Result<Option<HashMap<String, AttributeValue>>, <Self as Table>::Error> {
+        let output = client.query()
+            .table_name(Self::NAME)
+            .set_index_name(index_name.map(String::from))
+            .key_condition_expression("#key = :value")
+            .expression_attribute_names("#key", name)
+            .expression_attribute_values(":value", value)
+            .limit(1)
+            .send().await?;
+        Ok(output.items.unwrap_or_default().into_iter().next())
+    }
+
+    /// Builds the full key of the item with the primary key `pk`.
+    /// Tables with a sort key have it read from the stored item, so `None` means there is no such item.
+    async fn key(client: &Client, pk: Self::PK) -> Result<Option<HashMap<String, AttributeValue>>, <Self as Table>::Error> {
+        let pk = pk.into();
+        let mut key = HashMap::from([(Self::PK_NAME.to_string(), pk.clone())]);
+        if let Some(sort_name) = Self::SORT_NAME {
+            let item = Self::query_item(client, None, Self::PK_NAME, pk).await?;
+            match item.and_then(|mut item|item.remove(sort_name)) {
+                Some(sort) => key.insert(sort_name.to_string(), sort),
+                None => return Ok(None)
+            };
+        }
+        Ok(Some(key))
+    }
+
+    /// Inserts a new item into the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `item` - The item to be inserted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success or failure of the operation.
+    async fn create_item(client: &Client, item: Self) -> Result<(), <Self as Table>::Error> {
+        let _ = client.put_item()
+            .table_name(Self::NAME)
+            .set_item(Some(item.into()))
+            .send().await?;
+        Ok(())
+    }
+
+    /// Updates an item with the provided primary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `pk` - The primary key of the item.
+    /// * `update` - A `HashMap` containing the fields to update and their new values.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the updated item.
+    async fn update_item(client: &Client, pk: Self::PK, update: HashMap<String, impl Into<AttributeValue>>) -> Result<Self, <Self as Table>::Error> {
+        let key = Self::key(client, pk).await?.ok_or(ItemNotFound)?;
+        // DynamoDB rejects `AttributeUpdates` together with a condition expression, so the update is an expression too.
+        let mut builder = client.update_item()
+            .table_name(Self::NAME)
+            .set_key(Some(key))
+            .return_values(ReturnValue::AllNew)
+            .condition_expression("attribute_exists(#pk)")
+            .expression_attribute_names("#pk", Self::PK_NAME);
+
+        let mut assignments = Vec::new();
+        for (index, (key, value)) in update.into_iter().enumerate() {
+            assignments.push(format!("#f{index} = :v{index}"));
+            builder = builder
+                .expression_attribute_names(format!("#f{index}"), key)
+                .expression_attribute_values(format!(":v{index}"), value.into());
+        }
+
+        let output = builder.update_expression(format!("SET {}", assignments.join(", "))).send().await?;
+        match output.attributes {
+            None => Err(ItemNotFound.into()),
+            Some(map) => Ok(map.try_into()?),
+        }
+    }
+
+    /// Sets the attribute `name` of the item with the primary key `pk` to `new`, as long as it is still `old`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `pk` - The primary key of the item.
+    /// * `name` - The attribute to set.
+    /// * `old` - The value the attribute must still have.
+    /// * `new` - The value to set the attribute to.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `false` if the item doesn't exist or the attribute has changed in the meantime.
+    async fn swap_attribute(client: &Client, pk: Self::PK, name: &str, old: AttributeValue, new: AttributeValue) -> Result<bool, <Self as Table>::Error> {
+        let Some(key) = Self::key(client, pk).await? else {
+            return Ok(false)
+        };
+        let output = client.update_item()
+            .table_name(Self::NAME)
+            .set_key(Some(key))
+            .update_expression("SET #name = :new")
+            .condition_expression("#name = :old")
+            .expression_attribute_names("#name", name)
+            .expression_attribute_values(":old", old)
+            .expression_attribute_values(":new", new)
+            .send().await;
+        match output {
+            Ok(_) => Ok(true),
+            Err(err) => match err.as_service_error() {
+                Some(UpdateItemError::ConditionalCheckFailedException(_)) => Ok(false),
+                _ => Err(err.into())
+            }
+        }
+    }
+
+    /// Sets the attribute `key`, such as a TTL attribute, of the item with the provided primary key.
+    async fn expire_item(client: &Client, pk: Self::PK, (key, value): (impl Into<String>, impl Into<AttributeValue>)) -> Result<(), <Self as Table>::Error> {
+        let item_key = Self::key(client, pk).await?.ok_or(ItemNotFound)?;
+        let _ = client.update_item()
+            .table_name(Self::NAME)
+            .set_key(Some(item_key))
+            .update_expression("SET #key = :value")
+            .condition_expression(format!("attribute_exists({})", Self::PK_NAME))
+            .expression_attribute_names("#key", key)
+            .expression_attribute_values(":value", value.into())
+            .send().await?;
+        Ok(())
+    }
+
+    /// Deletes an item with the provided primary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `pk` - The primary key of the item.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success or failure of the operation.
+    async fn delete_item(client: &Client, pk: Self::PK) -> Result<(), <Self as Table>::Error> {
+        let Some(key) = Self::key(client, pk).await? else {
+            return Ok(())
+        };
+        let _ = client.delete_item()
+            .table_name(Self::NAME)
+            .set_key(Some(key))
+            .send().await?;
+        Ok(())
+    }
+}
+
+
+/// A `Table` whose Global Secondary Index has a sort key, so its items can be read by range.
+#[allow(async_fn_in_trait)]
+pub trait SortedIndex : Table {
+    /// The sort key of the Global Secondary Index.
+    const RANGE_NAME: &'static str;
+
+    /// Retrieves the items with the secondary key `sk` whose `RANGE_NAME` lies between `from` and `until`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A reference to the DynamoDB client.
+    /// * `sk` - The secondary key of the items.
+    /// * `from` - The lowest `RANGE_NAME` to include.
+    /// * `until` - The highest `RANGE_NAME` to include.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the items.
+    async fn query_items(client: &Client, sk: Self::SK, from: AttributeValue, until: AttributeValue) -> Result<Vec<Self>, <Self as Table>::Error> {
+        let sk = sk.into();
+        let mut items = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = client.query()
+                .table_name(Self::NAME)
+                .index_name(Self::INDEX_NAME)
+                .key_condition_expression("#key = :value AND #range BETWEEN :from AND :until")
+                .expression_attribute_names("#key", Self::SK_NAME)
+                .expression_attribute_names("#range", Self::RANGE_NAME)
+                .expression_attribute_values(":value", sk.clone())
+                .expression_attribute_values(":from", from.clone())
+                .expression_attribute_values(":until", until.clone())
+                .set_exclusive_start_key(start_key)
+                .send().await?;
+            for item in output.items.unwrap_or_default() {
+                items.push(item.try_into()?);
+            }
+            start_key = match output.last_evaluated_key {
+                Some(key) => Some(key),
+                None => return Ok(items)
+            };
+        }
+    }
+}
+
+
+impl Display for ItemNotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "item not found")
+    }
+}
+
+
+impl StdError for ItemNotFound {}
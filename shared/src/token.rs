@@ -0,0 +1,147 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use chrono::{Utc, DateTime};
+use serde_json::Value;
+use std::str::FromStr;
+
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>)
+}
+
+
+/// An access token issued by the authentication service, with its custom claims decoded into `T`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Token<T = HashMap<String, Value>> {
+    #[serde(rename = "jti")]
+    pub id: String,
+    #[serde(rename = "iss")]
+    pub issuer: String,
+    #[serde(rename = "sub")]
+    pub subject: String,
+    #[serde(rename = "aud")]
+    pub audience: Audience,
+    #[serde(rename = "exp")]
+    pub expiration: Option<DateTime<Utc>>,
+    #[serde(rename = "nbf")]
+    pub not_before: Option<DateTime<Utc>>,
+    #[serde(rename = "iat")]
+    pub issued_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub claims: T
+}
+
+
+/// The registered claims `Validation` checks, along with a way to look up custom claims.
+pub trait Claims {
+    fn issuer(&self) -> &str;
+    fn audience(&self) -> &Audience;
+    fn expiration(&self) -> Option<DateTime<Utc>>;
+    fn not_before(&self) -> Option<DateTime<Utc>>;
+    fn issued_at(&self) -> DateTime<Utc>;
+    /// Whether the custom claim `name` is present and not null.
+    fn has_claim(&self, name: &str) -> bool;
+}
+
+
+impl Audience {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Audience::One(aud) => aud.is_empty(),
+            Audience::Many(aud) => aud.is_empty()
+        }
+    }
+
+    pub fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == audience,
+            Audience::Many(aud) => aud.iter().any(|aud|aud == audience)
+        }
+    }
+}
+
+
+/// Parses a comma separated list of audiences.
+impl FromStr for Audience {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut audiences: Vec<String> = s.split(',').map(str::trim).filter(|aud|!aud.is_empty()).map(String::from).collect();
+        match audiences.len() {
+            0 => Err("audience cannot be empty"),
+            1 => Ok(Audience::One(audiences.remove(0))),
+            _ => Ok(Audience::Many(audiences))
+        }
+    }
+}
+
+
+impl<T: Serialize> Claims for Token<T> {
+    fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    fn audience(&self) -> &Audience {
+        &self.audience
+    }
+
+    fn expiration(&self) -> Option<DateTime<Utc>> {
+        self.expiration
+    }
+
+    fn not_before(&self) -> Option<DateTime<Utc>> {
+        self.not_before
+    }
+
+    fn issued_at(&self) -> DateTime<Utc> {
+        self.issued_at
+    }
+
+    fn has_claim(&self, name: &str) -> bool {
+        match serde_json::to_value(&self.claims) {
+            Ok(Value::Object(claims)) => claims.get(name).is_some_and(|claim|!claim.is_null()),
+            _ => false
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audience_from_str() {
+        assert_eq!("interphlix".parse(), Ok(Audience::One("interphlix".into())));
+        assert_eq!("web, mobile".parse(), Ok(Audience::Many(vec!["web".into(), "mobile".into()])));
+        assert!(" , ".parse::<Audience>().is_err());
+    }
+
+    #[test]
+    fn test_token_with_typed_claims() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Claims {
+            role: String,
+            tenant: Option<String>
+        }
+
+        let json = r#"{
+            "jti": "0f2c5b5cbd3a4b8a9f43a1f3c6a5b1e2",
+            "iss": "interphlix",
+            "sub": "507f1f77bcf86cd799439011",
+            "aud": ["web", "mobile"],
+            "exp": "2030-01-01T00:15:00Z",
+            "nbf": "2030-01-01T00:00:00Z",
+            "iat": "2030-01-01T00:00:00Z",
+            "role": "admin"
+        }"#;
+        let token: Token<Claims> = serde_json::from_str(json).unwrap();
+        assert_eq!(token.subject, "507f1f77bcf86cd799439011");
+        assert!(token.audience.contains("mobile"));
+        assert_eq!(token.claims, Claims{role: "admin".into(), tenant: None});
+        assert!(token.has_claim("role"));
+        assert!(!token.has_claim("tenant"));
+    }
+}
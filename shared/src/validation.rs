@@ -0,0 +1,177 @@
+use chrono::{Utc, DateTime, TimeDelta};
+use std::fmt::{Display, Formatter};
+use std::error::Error as StdError;
+use super::Claims;
+
+
+/// Why a token was rejected.
+#[derive(Debug)]
+pub enum VerifyError {
+    InvalidToken,
+    TokenExpired,
+    TokenNotYetValid,
+    WrongAudience,
+    /// The keyring could not be loaded.
+    KeyStore(Box<dyn StdError + Send + Sync>)
+}
+
+
+/// The claims a token has to satisfy to be accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validation {
+    /// The expected `iss`, any issuer is accepted when `None`.
+    pub issuer: Option<String>,
+    /// The token's `aud` has to contain at least one of these, any audience is accepted when empty.
+    pub audiences: Vec<String>,
+    /// How far the clocks of the issuer and the verifier may drift apart.
+    pub leeway: TimeDelta,
+    /// The maximum time since `iat` after which a token is considered expired, even if `exp` is later.
+    pub max_age: Option<TimeDelta>,
+    /// Custom claims every token has to carry.
+    pub required_claims: Vec<String>
+}
+
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation {
+            issuer: None,
+            audiences: Vec::new(),
+            leeway: TimeDelta::seconds(30),
+            max_age: None,
+            required_claims: Vec::new()
+        }
+    }
+}
+
+
+impl Validation {
+    /// Checks the registered and custom claims of `token` at the current time.
+    pub fn validate(&self, token: &impl Claims) -> Result<(), VerifyError> {
+        self.validate_at(token, Utc::now())
+    }
+
+    fn validate_at(&self, token: &impl Claims, now: DateTime<Utc>) -> Result<(), VerifyError> {
+        if let Some(expiration) = token.expiration() {
+            if now >= expiration + self.leeway {
+                return Err(VerifyError::TokenExpired);
+            }
+        }
+        if let Some(max_age) = self.max_age {
+            if now >= token.issued_at() + max_age + self.leeway {
+                return Err(VerifyError::TokenExpired);
+            }
+        }
+        if let Some(not_before) = token.not_before() {
+            if now + self.leeway < not_before {
+                return Err(VerifyError::TokenNotYetValid);
+            }
+        }
+        if now + self.leeway < token.issued_at() {
+            return Err(VerifyError::TokenNotYetValid);
+        }
+        if let Some(issuer) = &self.issuer {
+            if token.issuer() != issuer {
+                return Err(VerifyError::InvalidToken);
+            }
+        }
+        if !self.audiences.is_empty() && !self.audiences.iter().any(|audience|token.audience().contains(audience)) {
+            return Err(VerifyError::WrongAudience);
+        }
+        if !self.required_claims.iter().all(|claim|token.has_claim(claim)) {
+            return Err(VerifyError::InvalidToken);
+        }
+        Ok(())
+    }
+}
+
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidToken => write!(f, "invalid authorization token"),
+            VerifyError::TokenExpired => write!(f, "token expired"),
+            VerifyError::TokenNotYetValid => write!(f, "token not valid yet"),
+            VerifyError::WrongAudience => write!(f, "token has the wrong audience"),
+            VerifyError::KeyStore(err) => write!(f, "could not load the keyring: {err}")
+        }
+    }
+}
+
+
+impl StdError for VerifyError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Token, Audience};
+    use std::collections::HashMap;
+
+    fn token() -> Token {
+        let issued_at = Utc::now();
+        Token {
+            id: String::from("0f2c5b5cbd3a4b8a9f43a1f3c6a5b1e2"),
+            issuer: String::from("interphlix"),
+            subject: String::from("507f1f77bcf86cd799439011"),
+            audience: Audience::Many(vec!["web".into(), "mobile".into()]),
+            expiration: Some(issued_at + TimeDelta::minutes(15)),
+            not_before: Some(issued_at),
+            issued_at,
+            claims: HashMap::from([(String::from("role"), "admin".into())])
+        }
+    }
+
+    fn validation() -> Validation {
+        Validation {
+            issuer: Some("interphlix".into()),
+            audiences: vec!["mobile".into()],
+            max_age: Some(TimeDelta::hours(1)),
+            required_claims: vec!["role".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_token() {
+        assert!(validation().validate(&token()).is_ok());
+    }
+
+    #[test]
+    fn test_expired_token() {
+        let token = token();
+        let now = token.expiration.unwrap() + TimeDelta::minutes(1);
+        assert!(matches!(validation().validate_at(&token, now), Err(VerifyError::TokenExpired)));
+        let now = token.expiration.unwrap() + TimeDelta::seconds(10);
+        assert!(validation().validate_at(&token, now).is_ok());
+    }
+
+    #[test]
+    fn test_token_older_than_max_age() {
+        let mut token = token();
+        token.expiration = None;
+        let now = token.issued_at + TimeDelta::hours(2);
+        assert!(matches!(validation().validate_at(&token, now), Err(VerifyError::TokenExpired)));
+    }
+
+    #[test]
+    fn test_token_not_yet_valid() {
+        let token = token();
+        let now = token.not_before.unwrap() - TimeDelta::minutes(5);
+        assert!(matches!(validation().validate_at(&token, now), Err(VerifyError::TokenNotYetValid)));
+    }
+
+    #[test]
+    fn test_wrong_audience() {
+        let validation = Validation{audiences: vec!["admin".into()], ..validation()};
+        assert!(matches!(validation.validate(&token()), Err(VerifyError::WrongAudience)));
+    }
+
+    #[test]
+    fn test_wrong_issuer_and_missing_claim() {
+        let validation = Validation{issuer: Some("someone-else".into()), ..validation()};
+        assert!(matches!(validation.validate(&token()), Err(VerifyError::InvalidToken)));
+        let validation = Validation{required_claims: vec!["tenant".into()], ..Default::default()};
+        assert!(matches!(validation.validate(&token()), Err(VerifyError::InvalidToken)));
+    }
+}
@@ -0,0 +1,102 @@
+use rusty_paseto::core::{ImplicitAssertion, Footer, Key, Public, V4, Paseto, PasetoAsymmetricPublicKey};
+use super::{Keys, Keyring, KeyFooter, Token, Claims, Validation, VerifyError, Store, Parameters};
+use serde::{Serialize, de::DeserializeOwned};
+use std::sync::{RwLock, PoisonError};
+use chrono::{Utc, DateTime, TimeDelta};
+
+
+type Result<T> = std::result::Result<T, VerifyError>;
+
+
+/// Verifies the signature of `token` with the key named in its footer and returns its payload.
+/// Tokens without a footer were signed before key ids were introduced, so they are verified with the current key.
+pub fn verify(token: &str, keyring: &Keyring) -> Result<String> {
+    let (footer, key) = match KeyFooter::decode(token) {
+        Some((footer, key_footer)) => (Some(footer), keyring.find(&key_footer.kid)),
+        None => (None, keyring.current())
+    };
+    let key = key.ok_or(VerifyError::InvalidToken)?;
+    let key = Key::from(&key.key);
+    let public_key = PasetoAsymmetricPublicKey::<V4, Public>::from(&key);
+    let footer = footer.as_deref().map(Footer::from);
+    let implicit_assertion = Option::<ImplicitAssertion>::None;
+    Paseto::<V4, Public>::try_verify(token, &public_key, footer, implicit_assertion).map_err(|_|VerifyError::InvalidToken)
+}
+
+
+/// Verifies tokens locally with the public keyring, so services do not have to call the authentication service.
+///
+/// The keyring is cached until `keyrotator` is due to rotate the current key.
+/// A token signed with a key the cached keyring does not know about triggers a refetch,
+/// so tokens signed right after a rotation are accepted.
+#[derive(Debug)]
+pub struct Verifier {
+    store: Store,
+    parameters: Parameters,
+    validation: Validation,
+    cache: RwLock<Option<CachedKeyring>>
+}
+
+
+#[derive(Debug, Clone)]
+struct CachedKeyring {
+    keyring: Keyring,
+    fetched: DateTime<Utc>,
+    expires: DateTime<Utc>
+}
+
+
+impl Verifier {
+    /// The keyring is refetched at most this often, even if tokens name unknown keys.
+    const MIN_REFRESH_SECONDS: i64 = 30;
+    /// The keyring is never cached longer than this.
+    pub const MAX_AGE_MINUTES: i64 = 60;
+
+    pub fn new(store: impl Into<Store>, parameters: Parameters, validation: Validation) -> Self {
+        Self{store: store.into(), parameters, validation, cache: RwLock::new(None)}
+    }
+
+    /// Where the keyring is fetched from.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Verifies `token` and decodes its custom claims into `T`.
+    pub async fn verify<T: DeserializeOwned + Serialize>(&self, token: &str) -> Result<Token<T>> {
+        self.verify_claims(token).await
+    }
+
+    /// Verifies `token` and decodes its payload into any type that exposes the registered claims.
+    pub async fn verify_claims<C: DeserializeOwned + Claims>(&self, token: &str) -> Result<C> {
+        let kid = KeyFooter::decode(token).map(|(_, key_footer)|key_footer.kid);
+        let keyring = self.keyring(kid.as_deref()).await?;
+        let json = verify(token, &keyring)?;
+        let claims: C = serde_json::from_str(&json).map_err(|_|VerifyError::InvalidToken)?;
+        self.validation.validate(&claims)?;
+        Ok(claims)
+    }
+
+    /// Returns the cached keyring, fetching it when it has expired or does not contain `kid`.
+    pub async fn keyring(&self, kid: Option<&str>) -> Result<Keyring> {
+        let now = Utc::now();
+        if let Some(cached) = self.cache.read().unwrap_or_else(PoisonError::into_inner).as_ref() {
+            let fresh = now < cached.expires;
+            let known = kid.is_none_or(|kid|cached.keyring.find(kid).is_some());
+            let throttled = now < cached.fetched + TimeDelta::seconds(Self::MIN_REFRESH_SECONDS);
+            if fresh && (known || throttled) {
+                return Ok(cached.keyring.clone());
+            }
+        }
+        let keyring = match Keys::get_verifying(&self.store, &self.parameters).await {
+            Ok(Some(keyring)) => keyring,
+            Ok(None) => return Err(VerifyError::KeyStore("the keyring has not been created".into())),
+            Err(err) => return Err(VerifyError::KeyStore(err))
+        };
+        let max_age = now + TimeDelta::minutes(Self::MAX_AGE_MINUTES);
+        let min_age = now + TimeDelta::seconds(Self::MIN_REFRESH_SECONDS);
+        let expires = keyring.rotates_at().map_or(max_age, |rotates_at|rotates_at.min(max_age)).max(min_age);
+        let cached = CachedKeyring{keyring: keyring.clone(), fetched: now, expires};
+        *self.cache.write().unwrap_or_else(PoisonError::into_inner) = Some(cached);
+        Ok(keyring)
+    }
+}